@@ -1,33 +1,41 @@
-use crate::token::Token;
-use std::{
-    collections::{BTreeMap},
-    fmt::{self},
-};
+use crate::token::{Span, Token};
+use std::fmt::{self};
 
 pub enum Node {
     Program(Vec<Statement>),
-    Stat(Statement),
-    Expr(Expression),
 }
 
 impl fmt::Display for Node {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Node::Program(p) => write!(f, "{}", format(p, "")),
-            Node::Stat(s) => write!(f, "{}", s),
-            Node::Expr(e) => write!(f, "{}", e),
         }
     }
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Clone, Eq, Hash, Ord)]
-pub enum Statement {
-    Let(String, Expression),
+#[derive(Debug, PartialEq, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    pub span: Span,
+}
+
+impl Statement {
+    pub fn new(kind: StatementKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum StatementKind {
+    // `let x = value`, or a destructuring `let [a, b] = value`
+    Let(Pattern, Expression),
     Return(Expression),
     Expression(Expression),
+    Break,
+    Continue,
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Clone, Eq, Hash, Ord)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct BlockStatement(pub Vec<Statement>);
 impl fmt::Display for BlockStatement {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -36,51 +44,204 @@ impl fmt::Display for BlockStatement {
             res.push_str(&format!("{}", stat))
         }
 
-        return write!(f, "{}", res);
+        write!(f, "{}", res)
     }
 }
 
 impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+impl fmt::Display for StatementKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Statement::Let(identifier, expression) => {
-                return write!(f, "let {} = {};", identifier, expression);
+            StatementKind::Let(pattern, expression) => {
+                write!(f, "let {} = {};", pattern, expression)
             }
-            Statement::Return(expression) => {
-                return write!(f, "return {};", expression);
+            StatementKind::Return(expression) => {
+                write!(f, "return {};", expression)
             }
-            Statement::Expression(expression) => {
-                return write!(f, "{}", expression);
+            StatementKind::Expression(expression) => {
+                write!(f, "{}", expression)
             }
+            StatementKind::Break => write!(f, "break;"),
+            StatementKind::Continue => write!(f, "continue;"),
         }
     }
 }
-#[derive(Debug, PartialEq, PartialOrd, Clone, Eq, Hash, Ord)]
-pub enum Expression {
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub span: Span,
+}
+
+impl Expression {
+    pub fn new(kind: ExpressionKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExpressionKind {
     Identifier(String),
     Literal(Literal),
-    Prefix(Token, Box<Expression>),
-    Infix(Box<Expression>, Token, Box<Expression>),
+    Prefix(PrefixOperator, Box<Expression>),
+    Infix(Box<Expression>, InfixOperator, Box<Expression>),
+    // `&&` and `||`, which only evaluate their right operand when needed
+    Logical(Box<Expression>, LogicalOperator, Box<Expression>),
     Index(Box<Expression>, Box<Expression>),
     IfExpr(Box<Expression>, BlockStatement, Option<BlockStatement>),
-    Func(Option<Vec<String>>, BlockStatement),
-    FuncCall(Box<Expression>, Vec<Expression>),
+    While(Box<Expression>, BlockStatement),
+    // `for (x in items)`, or `for (k, v in items)` with the second variable
+    For(String, Option<String>, Box<Expression>, BlockStatement),
+    Func(Option<Vec<Parameter>>, BlockStatement),
+    // positional arguments, then `name: value` keyword arguments
+    FuncCall(Box<Expression>, Vec<Expression>, Vec<(String, Expression)>),
+    Template(Vec<TemplatePart>),
+    // `x = value`, or `x += value` and friends with the operator applied
+    Assign(String, Option<InfixOperator>, Box<Expression>),
+    Match(Box<Expression>, Vec<MatchArm>),
+    // `hash.key`, looking up the string key "key"
+    Member(Box<Expression>, String),
+    // `x.f(args)`, calling the function `f` in scope as `f(x, args)`
+    MethodCall(Box<Expression>, String, Vec<Expression>, Vec<(String, Expression)>),
 }
 
-impl fmt::Display for Expression {
+/// Parameter of a function literal.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Parameter {
+    // `x`, or `x = default` with the default evaluated by each call omitting it
+    Positional(String, Option<Expression>),
+    // `...rest`, collecting the remaining positional arguments into an array
+    Rest(String),
+}
+
+impl Parameter {
+    pub fn name(&self) -> &str {
+        match self {
+            Parameter::Positional(name, _) | Parameter::Rest(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Parameter::Positional(name, Some(default)) => write!(f, "{} = {}", name, default),
+            Parameter::Positional(name, None) => write!(f, "{}", name),
+            Parameter::Rest(name) => write!(f, "...{}", name),
+        }
+    }
+}
+
+/// `pattern if guard => body` arm of a `match` expression.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub guard: Option<Expression>,
+    pub body: Expression,
+}
+
+impl fmt::Display for MatchArm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.guard {
+            Some(guard) => write!(f, "{} if {} => {}", self.pattern, guard, self.body),
+            None => write!(f, "{} => {}", self.pattern, self.body),
+        }
+    }
+}
+
+/// Shape a value is tested against, binding the names it contains.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Pattern {
+    pub kind: PatternKind,
+    pub span: Span,
+}
+
+impl Pattern {
+    pub fn new(kind: PatternKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum PatternKind {
+    // `_`, which matches anything without binding it
+    Wildcard,
+    Binding(String),
+    // integer, float, boolean or string literal
+    Literal(Literal),
+    // `[a, b, ...rest]`, where the rest pattern is a binding or a wildcard
+    Array(Vec<Pattern>, Option<Box<Pattern>>),
+    // `{"key": pattern}`, ignoring keys that are not listed
+    Hash(Vec<(Literal, Pattern)>),
+}
+
+impl fmt::Display for PatternKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternKind::Wildcard => write!(f, "_"),
+            PatternKind::Binding(name) => write!(f, "{}", name),
+            PatternKind::Literal(literal) => write!(f, "{}", literal),
+            PatternKind::Array(elements, rest) => {
+                let mut elements: Vec<String> = elements.iter().map(|p| p.to_string()).collect();
+                if let Some(rest) = rest {
+                    elements.push(format!("...{}", rest));
+                }
+                write!(f, "[{}]", elements.join(", "))
+            }
+            PatternKind::Hash(pairs) => {
+                let pairs = pairs
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k, v))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                write!(f, "{{{}}}", pairs)
+            }
+        }
+    }
+}
+
+/// Piece of an interpolated string.
+#[derive(Debug, PartialEq, Clone)]
+pub enum TemplatePart {
+    Text(String),
+    Expression(Expression),
+}
+
+impl fmt::Display for ExpressionKind {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        return match self {
-            Expression::Identifier(identifier) => write!(f, "{}", identifier),
-            Expression::Literal(x) => write!(f, "{}", x),
-            Expression::Prefix(tok, expr) => write!(f, "({}{})", tok, expr),
-            Expression::Infix(left_expr, tok, right_expr) => {
+        match self {
+            ExpressionKind::Identifier(identifier) => write!(f, "{}", identifier),
+            ExpressionKind::Literal(x) => write!(f, "{}", x),
+            ExpressionKind::Prefix(tok, expr) => write!(f, "({}{})", tok, expr),
+            ExpressionKind::Infix(left_expr, tok, right_expr) => {
                 write!(f, "({}{}{})", left_expr, tok, right_expr)
             }
-            Expression::Index(left, index) => write!(f, "({}[{}])", left, index),
-            Expression::IfExpr(condition, consequence, alternative) => {
+            ExpressionKind::Logical(left_expr, operator, right_expr) => {
+                write!(f, "({}{}{})", left_expr, operator, right_expr)
+            }
+            ExpressionKind::Index(left, index) => write!(f, "({}[{}])", left, index),
+            ExpressionKind::IfExpr(condition, consequence, alternative) => {
                 let condition_expr = format!("{}", condition);
                 let consequence_expr = format!("{}", consequence);
-                return match alternative {
+                match alternative {
                     Some(alter) => {
                         write!(
                             f,
@@ -89,37 +250,214 @@ impl fmt::Display for Expression {
                         )
                     }
                     None => write!(f, "if {} {{ {} }}", condition_expr, consequence_expr),
-                };
+                }
+            }
+            ExpressionKind::While(condition, body) => {
+                write!(f, "while {} {{ {} }}", condition, body)
             }
-            Expression::Func(params, body) => match params {
+            ExpressionKind::For(first, second, iterable, body) => match second {
+                Some(second) => {
+                    write!(f, "for {}, {} in {} {{ {} }}", first, second, iterable, body)
+                }
+                None => write!(f, "for {} in {} {{ {} }}", first, iterable, body),
+            },
+            ExpressionKind::Func(params, body) => match params {
                 Some(params) => {
-                    write!(f, "fn({}) {{ {} }}", params.join(", "), body)
+                    write!(f, "fn({}) {{ {} }}", format(params, ", "), body)
                 }
                 None => write!(f, "fn() {{ {} }}", body),
             },
-            Expression::FuncCall(expression, arguments) => {
-                write!(f, "{}({})", expression, format(&arguments, ", "))
+            ExpressionKind::FuncCall(expression, arguments, keywords) => {
+                write!(f, "{}({})", expression, format_arguments(arguments, keywords))
             }
+            ExpressionKind::Template(parts) => {
+                write!(f, "\"")?;
+                for part in parts {
+                    match part {
+                        TemplatePart::Text(text) => {
+                            write!(f, "{}", text.escape_debug().to_string().replace('$', "\\$"))?
+                        }
+                        TemplatePart::Expression(expression) => write!(f, "${{{}}}", expression)?,
+                    }
+                }
+                write!(f, "\"")
+            }
+            ExpressionKind::Assign(name, operator, value) => match operator {
+                Some(operator) => write!(f, "({} {}= {})", name, operator, value),
+                None => write!(f, "({} = {})", name, value),
+            },
+            ExpressionKind::Match(subject, arms) => {
+                write!(f, "match {} {{ {} }}", subject, format(arms, ", "))
+            }
+            ExpressionKind::Member(object, key) => write!(f, "({}.{})", object, key),
+            ExpressionKind::MethodCall(receiver, name, arguments, keywords) => write!(
+                f,
+                "{}.{}({})",
+                receiver,
+                name,
+                format_arguments(arguments, keywords)
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PrefixOperator {
+    Bang,
+    Minus,
+    Tilde,
+}
+
+impl PrefixOperator {
+    pub fn from_token(token: &Token) -> Option<Self> {
+        match token {
+            Token::BANG => Some(Self::Bang),
+            Token::MINUS => Some(Self::Minus),
+            Token::TILDE => Some(Self::Tilde),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for PrefixOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bang => write!(f, "!"),
+            Self::Minus => write!(f, "-"),
+            Self::Tilde => write!(f, "~"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum InfixOperator {
+    Plus,
+    Minus,
+    Asterisk,
+    Slash,
+    Percent,
+    Power,
+    Lt,
+    Gt,
+    LtEq,
+    GtEq,
+    Eq,
+    NotEq,
+    BitAnd,
+    BitOr,
+    BitXor,
+    Shl,
+    Shr,
+}
+
+impl InfixOperator {
+    pub fn from_token(token: &Token) -> Option<Self> {
+        let operator = match token {
+            Token::PLUS => Self::Plus,
+            Token::MINUS => Self::Minus,
+            Token::ASTERISK => Self::Asterisk,
+            Token::SLASH => Self::Slash,
+            Token::PERCENT => Self::Percent,
+            Token::POWER => Self::Power,
+            Token::LT => Self::Lt,
+            Token::GT => Self::Gt,
+            Token::LTEQ => Self::LtEq,
+            Token::GTEQ => Self::GtEq,
+            Token::EQ => Self::Eq,
+            Token::NOTEQ => Self::NotEq,
+            Token::AMPERSAND => Self::BitAnd,
+            Token::PIPE => Self::BitOr,
+            Token::CARET => Self::BitXor,
+            Token::SHL => Self::Shl,
+            Token::SHR => Self::Shr,
+            _ => return None,
+        };
+
+        Some(operator)
+    }
+
+    /// Operator applied by a compound assignment such as `+=`.
+    pub fn from_assignment_token(token: &Token) -> Option<Self> {
+        let operator = match token {
+            Token::PLUSASSIGN => Self::Plus,
+            Token::MINUSASSIGN => Self::Minus,
+            Token::ASTERISKASSIGN => Self::Asterisk,
+            Token::SLASHASSIGN => Self::Slash,
+            _ => return None,
         };
+
+        Some(operator)
     }
 }
 
-#[derive(Debug, PartialEq, Clone, Eq, PartialOrd, Hash, Ord)]
+impl fmt::Display for InfixOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Plus => write!(f, "+"),
+            Self::Minus => write!(f, "-"),
+            Self::Asterisk => write!(f, "*"),
+            Self::Slash => write!(f, "/"),
+            Self::Percent => write!(f, "%"),
+            Self::Power => write!(f, "**"),
+            Self::Lt => write!(f, "<"),
+            Self::Gt => write!(f, ">"),
+            Self::LtEq => write!(f, "<="),
+            Self::GtEq => write!(f, ">="),
+            Self::Eq => write!(f, "=="),
+            Self::NotEq => write!(f, "!="),
+            Self::BitAnd => write!(f, "&"),
+            Self::BitOr => write!(f, "|"),
+            Self::BitXor => write!(f, "^"),
+            Self::Shl => write!(f, "<<"),
+            Self::Shr => write!(f, ">>"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LogicalOperator {
+    And,
+    Or,
+}
+
+impl LogicalOperator {
+    pub fn from_token(token: &Token) -> Option<Self> {
+        match token {
+            Token::AND => Some(Self::And),
+            Token::OR => Some(Self::Or),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for LogicalOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::And => write!(f, "&&"),
+            Self::Or => write!(f, "||"),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum Literal {
     Integer(i64),
+    Float(f64),
     Bool(bool),
     String(String),
     Array(Vec<Expression>),
-    Hash(BTreeMap<Expression, Expression>),
+    Hash(Vec<(Expression, Expression)>),
 }
 
 impl fmt::Display for Literal {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::Integer(int) => write!(f, "{}", int),
+            Self::Float(float) => write!(f, "{:?}", float),
             Self::Bool(bool) => write!(f, "{}", bool),
-            Self::String(s) => write!(f, "\"{}\"", s),
-            Self::Array(exprs) => write!(f, "{}", format(&exprs, ", ")),
+            // Debug output escapes quotes and control characters the same way the lexer reads them
+            Self::String(s) => write!(f, "{:?}", s),
+            Self::Array(exprs) => write!(f, "{}", format(exprs, ", ")),
             Self::Hash(map) => {
                 let map = map
                     .iter()
@@ -133,7 +471,13 @@ impl fmt::Display for Literal {
     }
 }
 
-fn format<T: fmt::Display>(f: &Vec<T>, seperator: &str) -> String {
+fn format_arguments(arguments: &[Expression], keywords: &[(String, Expression)]) -> String {
+    let mut res: Vec<String> = arguments.iter().map(|arg| arg.to_string()).collect();
+    res.extend(keywords.iter().map(|(name, value)| format!("{}: {}", name, value)));
+    res.join(", ")
+}
+
+fn format<T: fmt::Display>(f: &[T], seperator: &str) -> String {
     f.iter()
         .map(|stmt| stmt.to_string())
         .collect::<Vec<String>>()
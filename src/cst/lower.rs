@@ -0,0 +1,436 @@
+use crate::{
+    ast::*,
+    parser::{parse_embedded_syntax_tree, ParseError},
+    token::{Span, StringPart, Token},
+};
+
+use super::{SyntaxElement, SyntaxKind, SyntaxNode, SyntaxToken};
+
+// positional and keyword arguments of a call
+type Arguments = (Vec<Expression>, Vec<(String, Expression)>);
+
+/// Derives the `ast` statements of a program from its syntax tree.
+///
+/// This is the only way the AST is built: the parser produces nothing but
+/// the tree. `source` is the text the tree was parsed from, which is needed
+/// to parse the `${...}` interpolations held inside string tokens. A tree
+/// that does not have the shape the parser gives an error-free program, such
+/// as one containing an `Error` node, is reported at its first bad node.
+pub fn lower(source: &str, tree: &SyntaxNode) -> Result<Vec<Statement>, ParseError> {
+    Lowering { source }.statements(tree)
+}
+
+/// Derives the `ast` expression held by a single node of a syntax tree.
+pub fn lower_expression(source: &str, node: &SyntaxNode) -> Result<Expression, ParseError> {
+    Lowering { source }.expression(node)
+}
+
+/// Name that `target = value` assigns to, when `target` is an identifier,
+/// possibly in parentheses.
+pub(crate) fn assigned_name<'a>(target: &SyntaxNode<'a>) -> Option<&'a str> {
+    match target.kind {
+        SyntaxKind::Identifier => find_identifier(target),
+        SyntaxKind::GroupExpression => assigned_name(Children::new(target).next()?),
+        _ => None,
+    }
+}
+
+struct Lowering<'s> {
+    source: &'s str,
+}
+
+impl Lowering<'_> {
+    fn statements(&self, node: &SyntaxNode) -> Result<Vec<Statement>, ParseError> {
+        Children::new(node)
+            .map(|statement| self.statement(statement))
+            .collect()
+    }
+
+    fn statement(&self, node: &SyntaxNode) -> Result<Statement, ParseError> {
+        let mut children = Children::new(node);
+        let kind = match node.kind {
+            SyntaxKind::LetStatement => {
+                let pattern = self.pattern(children.expect()?)?;
+                StatementKind::Let(pattern, self.expression(children.expect()?)?)
+            }
+            SyntaxKind::ReturnStatement => StatementKind::Return(self.expression(children.expect()?)?),
+            SyntaxKind::ExpressionStatement => {
+                StatementKind::Expression(self.expression(children.expect()?)?)
+            }
+            SyntaxKind::BreakStatement => StatementKind::Break,
+            SyntaxKind::ContinueStatement => StatementKind::Continue,
+            _ => return Err(malformed(node)),
+        };
+
+        Ok(Statement::new(kind, span(node)?))
+    }
+
+    fn block(&self, node: &SyntaxNode) -> Result<BlockStatement, ParseError> {
+        match node.kind {
+            SyntaxKind::BlockStatement => Ok(BlockStatement(self.statements(node)?)),
+            _ => Err(malformed(node)),
+        }
+    }
+
+    fn expression(&self, node: &SyntaxNode) -> Result<Expression, ParseError> {
+        let mut children = Children::new(node);
+        let kind = match node.kind {
+            SyntaxKind::Identifier => ExpressionKind::Identifier(identifier(node)?),
+            SyntaxKind::Literal => {
+                ExpressionKind::Literal(literal(token(node)?).ok_or_else(|| malformed(node))?)
+            }
+            SyntaxKind::PrefixExpression => {
+                let operator =
+                    PrefixOperator::from_token(&token(node)?.token).ok_or_else(|| malformed(node))?;
+                ExpressionKind::Prefix(operator, Box::new(self.expression(children.expect()?)?))
+            }
+            SyntaxKind::InfixExpression => {
+                let left = Box::new(self.expression(children.expect()?)?);
+                let right = Box::new(self.expression(children.expect()?)?);
+                let token = &token(node)?.token;
+                match LogicalOperator::from_token(token) {
+                    Some(operator) => ExpressionKind::Logical(left, operator, right),
+                    None => {
+                        let operator = InfixOperator::from_token(token).ok_or_else(|| malformed(node))?;
+                        ExpressionKind::Infix(left, operator, right)
+                    }
+                }
+            }
+            // the parentheses only widen the span of the inner expression
+            SyntaxKind::GroupExpression => self.expression(children.expect()?)?.kind,
+            SyntaxKind::ArrayLiteral => {
+                let elements = children.map(|element| self.expression(element));
+                ExpressionKind::Literal(Literal::Array(elements.collect::<Result<_, _>>()?))
+            }
+            SyntaxKind::HashLiteral => {
+                let mut pairs = vec![];
+                while let Some(key) = children.next() {
+                    pairs.push((self.expression(key)?, self.expression(children.expect()?)?));
+                }
+                ExpressionKind::Literal(Literal::Hash(pairs))
+            }
+            SyntaxKind::IfExpression => {
+                let condition = self.expression(children.expect()?)?;
+                let consequence = self.block(children.expect()?)?;
+                let alternative = match children.next() {
+                    // `else if` is an `else` block holding just the nested if
+                    Some(nested) if nested.kind == SyntaxKind::IfExpression => {
+                        Some(single_expression_block(self.expression(nested)?))
+                    }
+                    Some(block) => Some(self.block(block)?),
+                    None => None,
+                };
+                ExpressionKind::IfExpr(Box::new(condition), consequence, alternative)
+            }
+            // `cond ? a : b` is an if expression whose branches hold one
+            // expression each
+            SyntaxKind::ConditionalExpression => {
+                let condition = self.expression(children.expect()?)?;
+                let consequence = self.expression(children.expect()?)?;
+                let alternative = self.expression(children.expect()?)?;
+                ExpressionKind::IfExpr(
+                    Box::new(condition),
+                    single_expression_block(consequence),
+                    Some(single_expression_block(alternative)),
+                )
+            }
+            SyntaxKind::WhileExpression => {
+                let condition = self.expression(children.expect()?)?;
+                ExpressionKind::While(Box::new(condition), self.block(children.expect()?)?)
+            }
+            SyntaxKind::ForExpression => {
+                let mut names = tokens(node).filter_map(|token| match token.token {
+                    Token::IDENT(name) => Some(name.to_string()),
+                    _ => None,
+                });
+                let first = names.next().ok_or_else(|| malformed(node))?;
+                let second = names.next();
+                let iterable = self.expression(children.expect()?)?;
+                ExpressionKind::For(first, second, Box::new(iterable), self.block(children.expect()?)?)
+            }
+            SyntaxKind::FunctionExpression => {
+                let params = self.parameters(children.expect()?)?;
+                let body = children.expect()?;
+                let body = match body.kind {
+                    SyntaxKind::BlockStatement => self.block(body)?,
+                    // the single expression of `|x| expr` or `fn(x) => expr`
+                    _ => single_expression_block(self.expression(body)?),
+                };
+                ExpressionKind::Func(params, body)
+            }
+            SyntaxKind::CallExpression => {
+                let function = self.expression(children.expect()?)?;
+                let (args, keywords) = self.arguments(children.expect()?)?;
+                ExpressionKind::FuncCall(Box::new(function), args, keywords)
+            }
+            SyntaxKind::IndexExpression => {
+                let left = self.expression(children.expect()?)?;
+                ExpressionKind::Index(Box::new(left), Box::new(self.expression(children.expect()?)?))
+            }
+            SyntaxKind::MemberExpression => {
+                let object = self.expression(children.expect()?)?;
+                ExpressionKind::Member(Box::new(object), identifier(node)?)
+            }
+            SyntaxKind::MethodCallExpression => {
+                let receiver = self.expression(children.expect()?)?;
+                let (args, keywords) = self.arguments(children.expect()?)?;
+                ExpressionKind::MethodCall(Box::new(receiver), identifier(node)?, args, keywords)
+            }
+            SyntaxKind::AssignExpression => {
+                let target = children.expect()?;
+                let name = assigned_name(target).ok_or_else(|| malformed(target))?;
+                let operator = InfixOperator::from_assignment_token(&token(node)?.token);
+                let value = self.expression(children.expect()?)?;
+                ExpressionKind::Assign(name.to_string(), operator, Box::new(value))
+            }
+            // `x |> f(a)` is `f(x, a)`, and `x |> f` is `f(x)`
+            SyntaxKind::PipelineExpression => {
+                let value = self.expression(children.expect()?)?;
+                let target = self.expression(children.expect()?)?;
+                match target.kind {
+                    ExpressionKind::FuncCall(function, mut args, keywords) => {
+                        args.insert(0, value);
+                        ExpressionKind::FuncCall(function, args, keywords)
+                    }
+                    _ => ExpressionKind::FuncCall(Box::new(target), vec![value], vec![]),
+                }
+            }
+            SyntaxKind::MatchExpression => {
+                let subject = self.expression(children.expect()?)?;
+                let arms = children.map(|arm| self.match_arm(arm));
+                ExpressionKind::Match(Box::new(subject), arms.collect::<Result<_, _>>()?)
+            }
+            SyntaxKind::TemplateString => {
+                let Token::TEMPLATE(parts) = &token(node)?.token else {
+                    return Err(malformed(node));
+                };
+                let parts = parts
+                    .iter()
+                    .map(|part| self.template_part(part))
+                    .collect::<Result<_, _>>()?;
+                ExpressionKind::Template(parts)
+            }
+            _ => return Err(malformed(node)),
+        };
+
+        Ok(Expression::new(kind, span(node)?))
+    }
+
+    // The expression of a `${...}` interpolation is parsed again from the
+    // source, as the string token holds only its span.
+    fn template_part(&self, part: &StringPart) -> Result<TemplatePart, ParseError> {
+        match part {
+            StringPart::Text(text) => Ok(TemplatePart::Text(text.to_string())),
+            StringPart::Expression(span) => {
+                let tree = parse_embedded_syntax_tree(self.source, *span);
+                let expression = self.expression(Children::new(&tree).expect()?)?;
+                Ok(TemplatePart::Expression(expression))
+            }
+        }
+    }
+
+    // `None` for an empty list, as in `fn() {}` or `|| 1`.
+    fn parameters(&self, node: &SyntaxNode) -> Result<Option<Vec<Parameter>>, ParseError> {
+        if node.kind != SyntaxKind::ParameterList {
+            return Err(malformed(node));
+        }
+        let params: Vec<Parameter> = Children::new(node)
+            .map(|param| self.parameter(param))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Some(params).filter(|params| !params.is_empty()))
+    }
+
+    fn parameter(&self, node: &SyntaxNode) -> Result<Parameter, ParseError> {
+        let name = identifier(node)?;
+        if tokens(node).any(|token| token.token == Token::ELLIPSIS) {
+            return Ok(Parameter::Rest(name));
+        }
+        let default = match Children::new(node).next() {
+            Some(default) => Some(self.expression(default)?),
+            None => None,
+        };
+
+        Ok(Parameter::Positional(name, default))
+    }
+
+    fn arguments(&self, node: &SyntaxNode) -> Result<Arguments, ParseError> {
+        if node.kind != SyntaxKind::ArgumentList {
+            return Err(malformed(node));
+        }
+        let mut args = vec![];
+        let mut keywords = vec![];
+        for argument in Children::new(node) {
+            match argument.kind {
+                SyntaxKind::KeywordArgument => {
+                    let value = self.expression(Children::new(argument).expect()?)?;
+                    keywords.push((identifier(argument)?, value));
+                }
+                _ => args.push(self.expression(argument)?),
+            }
+        }
+
+        Ok((args, keywords))
+    }
+
+    fn match_arm(&self, node: &SyntaxNode) -> Result<MatchArm, ParseError> {
+        if node.kind != SyntaxKind::MatchArm {
+            return Err(malformed(node));
+        }
+        let mut children = Children::new(node);
+        let pattern = self.pattern(children.expect()?)?;
+        let guard = if tokens(node).any(|token| token.token == Token::IF) {
+            Some(self.expression(children.expect()?)?)
+        } else {
+            None
+        };
+
+        Ok(MatchArm {
+            pattern,
+            guard,
+            body: self.expression(children.expect()?)?,
+        })
+    }
+
+    fn pattern(&self, node: &SyntaxNode) -> Result<Pattern, ParseError> {
+        let kind = match node.kind {
+            SyntaxKind::WildcardPattern => PatternKind::Wildcard,
+            SyntaxKind::BindingPattern => PatternKind::Binding(identifier(node)?),
+            SyntaxKind::LiteralPattern => PatternKind::Literal(literal_pattern(node)?),
+            // `...name` binds the rest, a bare `...` or `..._` ignores it
+            SyntaxKind::RestPattern => match find_identifier(node) {
+                Some(name) if name != "_" => PatternKind::Binding(name.to_string()),
+                _ => PatternKind::Wildcard,
+            },
+            SyntaxKind::ArrayPattern => {
+                let mut elements = vec![];
+                let mut rest = None;
+                for element in Children::new(node) {
+                    let pattern = self.pattern(element)?;
+                    match element.kind {
+                        SyntaxKind::RestPattern => rest = Some(Box::new(pattern)),
+                        _ => elements.push(pattern),
+                    }
+                }
+                PatternKind::Array(elements, rest)
+            }
+            // each key is a literal pattern node followed by its value's pattern
+            SyntaxKind::HashPattern => {
+                let mut pairs = vec![];
+                let mut children = Children::new(node);
+                while let Some(key) = children.next() {
+                    if key.kind != SyntaxKind::LiteralPattern {
+                        return Err(malformed(key));
+                    }
+                    pairs.push((literal_pattern(key)?, self.pattern(children.expect()?)?));
+                }
+                PatternKind::Hash(pairs)
+            }
+            _ => return Err(malformed(node)),
+        };
+
+        Ok(Pattern::new(kind, span(node)?))
+    }
+}
+
+/// Child nodes of `parent` in source order, skipping its tokens.
+struct Children<'n, 'a> {
+    parent: &'n SyntaxNode<'a>,
+    elements: std::slice::Iter<'n, SyntaxElement<'a>>,
+}
+
+impl<'n, 'a> Children<'n, 'a> {
+    fn new(parent: &'n SyntaxNode<'a>) -> Self {
+        Self {
+            parent,
+            elements: parent.children.iter(),
+        }
+    }
+
+    // The next child node, which the parent cannot do without.
+    fn expect(&mut self) -> Result<&'n SyntaxNode<'a>, ParseError> {
+        self.next().ok_or_else(|| malformed(self.parent))
+    }
+}
+
+impl<'n, 'a> Iterator for Children<'n, 'a> {
+    type Item = &'n SyntaxNode<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.elements.find_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+}
+
+fn malformed(node: &SyntaxNode) -> ParseError {
+    ParseError::new(
+        format!("Unexpected {:?} node in the syntax tree", node.kind),
+        node.span().unwrap_or_default(),
+    )
+}
+
+fn span(node: &SyntaxNode) -> Result<Span, ParseError> {
+    node.span().ok_or_else(|| malformed(node))
+}
+
+fn single_expression_block(expression: Expression) -> BlockStatement {
+    let span = expression.span;
+    BlockStatement(vec![Statement::new(StatementKind::Expression(expression), span)])
+}
+
+// Tokens among the children of `node`, without trivia.
+fn tokens<'n, 'a>(node: &'n SyntaxNode<'a>) -> impl Iterator<Item = &'n SyntaxToken<'a>> {
+    node.children.iter().filter_map(|child| match child {
+        SyntaxElement::Token(token) if !token.is_trivia() => Some(token),
+        _ => None,
+    })
+}
+
+// The first significant token among the children of `node`.
+fn token<'n, 'a>(node: &'n SyntaxNode<'a>) -> Result<&'n SyntaxToken<'a>, ParseError> {
+    tokens(node).next().ok_or_else(|| malformed(node))
+}
+
+// The first name among the tokens of `node`.
+fn find_identifier<'a>(node: &SyntaxNode<'a>) -> Option<&'a str> {
+    tokens(node).find_map(|token| match token.token {
+        Token::IDENT(name) => Some(name),
+        _ => None,
+    })
+}
+
+fn identifier(node: &SyntaxNode) -> Result<String, ParseError> {
+    find_identifier(node)
+        .map(str::to_string)
+        .ok_or_else(|| malformed(node))
+}
+
+fn literal(token: &SyntaxToken) -> Option<Literal> {
+    let literal = match &token.token {
+        Token::INT(i) => Literal::Integer(*i),
+        Token::FLOAT(x) => Literal::Float(*x),
+        Token::BOOLEAN(b) => Literal::Bool(*b),
+        Token::STRING(s) => Literal::String(s.to_string()),
+        _ => return None,
+    };
+
+    Some(literal)
+}
+
+// A literal in a pattern, where a number may be negated as in `-1`.
+fn literal_pattern(node: &SyntaxNode) -> Result<Literal, ParseError> {
+    let tokens: Vec<&SyntaxToken> = tokens(node).collect();
+    let literal = match tokens[..] {
+        [token] => literal(token),
+        [minus, token] if minus.token == Token::MINUS => match literal(token) {
+            Some(Literal::Integer(i)) => Some(Literal::Integer(-i)),
+            Some(Literal::Float(x)) => Some(Literal::Float(-x)),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    literal.ok_or_else(|| malformed(node))
+}
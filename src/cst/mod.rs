@@ -0,0 +1,548 @@
+use std::fmt;
+
+use crate::token::{Span, SpannedToken, Token};
+
+mod lower;
+pub use self::lower::{lower, lower_expression};
+pub(crate) use self::lower::assigned_name;
+
+/// Kinds of the interior nodes of the concrete syntax tree.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SyntaxKind {
+    Program,
+    LetStatement,
+    ReturnStatement,
+    ExpressionStatement,
+    BreakStatement,
+    ContinueStatement,
+    BlockStatement,
+    Identifier,
+    Literal,
+    PrefixExpression,
+    InfixExpression,
+    GroupExpression,
+    ArrayLiteral,
+    HashLiteral,
+    IfExpression,
+    // `cond ? a : b`
+    ConditionalExpression,
+    WhileExpression,
+    ForExpression,
+    FunctionExpression,
+    ParameterList,
+    Parameter,
+    CallExpression,
+    ArgumentList,
+    // `name: value` in an argument list
+    KeywordArgument,
+    IndexExpression,
+    MemberExpression,
+    MethodCallExpression,
+    AssignExpression,
+    // `x |> f(a)`, parsed as the call `f(x, a)`
+    PipelineExpression,
+    MatchExpression,
+    MatchArm,
+    WildcardPattern,
+    BindingPattern,
+    LiteralPattern,
+    ArrayPattern,
+    HashPattern,
+    // `...rest` at the end of an array pattern
+    RestPattern,
+    // the interpolated expressions are part of the string token
+    TemplateString,
+    // a statement that failed to parse, kept so no source text is lost
+    Error,
+}
+
+/// Lossless concrete syntax tree.
+///
+/// Every byte of the source, including whitespace and comments, is held by
+/// exactly one token, so printing the tree reproduces the input verbatim.
+/// Whitespace and comments between two nodes are kept as siblings of those
+/// nodes rather than inside them, and each node covers the same source range
+/// as the `ast` node lowered from it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxNode<'a> {
+    pub kind: SyntaxKind,
+    pub children: Vec<SyntaxElement<'a>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SyntaxElement<'a> {
+    Node(SyntaxNode<'a>),
+    Token(SyntaxToken<'a>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SyntaxToken<'a> {
+    pub token: Token<'a>,
+    pub text: &'a str,
+    pub span: Span,
+}
+
+impl SyntaxToken<'_> {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.token, Token::WHITESPACE | Token::COMMENT)
+    }
+}
+
+impl<'a> SyntaxNode<'a> {
+    /// Tokens of this node in source order, trivia included.
+    pub fn tokens(&self) -> Vec<&SyntaxToken<'a>> {
+        let mut tokens = vec![];
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+
+        tokens
+    }
+
+    /// Source range from the first to the last token of the node.
+    pub fn span(&self) -> Option<Span> {
+        let tokens = self.tokens();
+        let first = tokens.first()?;
+        let last = tokens.last()?;
+
+        Some(first.span.to(last.span))
+    }
+}
+
+impl fmt::Display for SyntaxNode<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => write!(f, "{}", node)?,
+                SyntaxElement::Token(token) => write!(f, "{}", token.text)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds a `SyntaxNode` bottom-up while the parser consumes tokens.
+///
+/// Tokens are appended to a flat list as the parser reaches them, and a
+/// finished construct is turned into a node by wrapping everything after a
+/// checkpoint taken at its first token. Nodes are therefore only created
+/// once their contents are known, which lets the Pratt parser wrap an
+/// already parsed left operand into an infix, call or index node.
+pub struct SyntaxTreeBuilder<'a> {
+    source: &'a str,
+    // end of the source covered so far
+    offset: usize,
+    children: Vec<SyntaxElement<'a>>,
+}
+
+impl<'a> SyntaxTreeBuilder<'a> {
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            offset: 0,
+            children: vec![],
+        }
+    }
+
+    pub fn token(&mut self, token: &SpannedToken<'a>) {
+        self.skipped(token.span.start);
+        self.push(token.token.clone(), token.span);
+    }
+
+    // Keeps source the lexer skipped while recovering from an error, such as
+    // the rest of a string after a bad escape, as an `ILLEGAL` token.
+    fn skipped(&mut self, end: usize) {
+        if end <= self.offset {
+            return;
+        }
+        let before = &self.source[..self.offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        let column = before[line_start..].chars().count() + 1;
+        let span = Span {
+            start: self.offset,
+            end,
+            line,
+            column,
+        };
+        self.push(Token::ILLEGAL, span);
+    }
+
+    fn push(&mut self, token: Token<'a>, span: Span) {
+        self.children.push(SyntaxElement::Token(SyntaxToken {
+            token,
+            text: &self.source[span.start..span.end],
+            span,
+        }));
+        self.offset = span.end;
+    }
+
+    /// Position of the most recent significant token, i.e. the parser's
+    /// current token.
+    pub fn checkpoint(&self) -> usize {
+        self.children
+            .iter()
+            .rposition(|child| !is_trivia(child))
+            .unwrap_or(0)
+    }
+
+    /// Wraps everything from `checkpoint` onwards into a node of `kind`,
+    /// leaving trailing trivia outside of it.
+    pub fn wrap(&mut self, checkpoint: usize, kind: SyntaxKind) {
+        self.wrap_until(checkpoint, self.children.len(), kind)
+    }
+
+    /// Like `wrap`, but leaves the elements from `end` onwards outside of
+    /// the node.
+    pub fn wrap_until(&mut self, checkpoint: usize, end: usize, kind: SyntaxKind) {
+        let rest = self.children.split_off(end);
+        let mut children = self.children.split_off(checkpoint);
+        let content_len = children
+            .iter()
+            .rposition(|child| !is_trivia(child))
+            .map_or(0, |i| i + 1);
+        let trailing = children.split_off(content_len);
+
+        self.children
+            .push(SyntaxElement::Node(SyntaxNode { kind, children }));
+        self.children.extend(trailing);
+        self.children.extend(rest);
+    }
+
+    /// The node `wrap` created at `checkpoint`.
+    pub fn node_at(&self, checkpoint: usize) -> Option<&SyntaxNode<'a>> {
+        match self.children.get(checkpoint)? {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        }
+    }
+
+    pub fn finish(mut self) -> SyntaxNode<'a> {
+        self.skipped(self.source.len());
+        SyntaxNode {
+            kind: SyntaxKind::Program,
+            children: self.children,
+        }
+    }
+}
+
+fn is_trivia(element: &SyntaxElement) -> bool {
+    match element {
+        SyntaxElement::Token(token) => token.is_trivia(),
+        SyntaxElement::Node(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::{
+        ast::*,
+        parser::{parse_embedded_syntax_tree, parse_syntax_tree},
+        token::StringPart,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let inputs = vec![
+            "let x = 5;",
+            "  let   add = fn(a, b) { a + b };  // adds\nadd(1, 2)\n",
+            "/* header */\nlet arr = [1, 2 , 3];\narr[ 1 ]",
+            "if (x < y) {\n    x\n} else { y } /* trailing */",
+            "let h = {\"a\": 1, \"b\": [2, 3]};\n\n",
+            "-(1 + 2) * !true",
+            "for (k, v in {1: 2}) { /* body */ k }",
+            "while (i < 3) {\n  i += 1; // step\n  if (i == 2) { break; } continue\n}",
+            "let n = 1;\nn += 2 ; n  =  n /= 3",
+            "if (a) { 1 }\nelse  if (b) { 2 } /* c */ else if(c){3}",
+            "let m = a>b ?a: /* else */ b ? 1:2;",
+            "let [ a, ...rest ] = xs; let {\"k\": v} = h;",
+            "let f = fn(x, y = 2 /* two */, ... more) { x };\nf(1, y : 3)",
+            "map(xs, | x , y | x+y); let g = || 1; let h = fn (x)=> x",
+            "arr . rest()\n  .push(1, at: 0) // chained\n  .len(); h.key",
+            "data\n  |> keep(ok) // valid only\n  |> len",
+            "match ( x ) {\n  [ a, ...rest ] if a > 0 => a,\n  {\"k\" : -1} => 0, // neg\n  _=>1,\n}",
+            "\"hi ${ name /* who */ }\"",
+            "let q = r#\"raw \\n\"#;\nlet m = \"\"\"\n    indented\n    \"\"\";",
+            "",
+            "   \n// only a comment",
+        ];
+
+        for input in inputs {
+            let (program, tree) = parse_syntax_tree(input);
+            assert!(program.is_ok(), "{}", input);
+            assert_eq!(input, tree.to_string());
+        }
+    }
+
+    #[test]
+    fn test_round_trip_with_errors() {
+        let inputs = vec![
+            "let = 5; let y = 1;",
+            "\"bad \\q escape\" + 1",
+            "let s = \"unterminated",
+            "1 + /* unterminated",
+            "fn(1) { x }",
+            "let f = fn() {\n  x + ; // oops\n  let y = ;\n};\nlet h = {1: };",
+            "if (x) { 1 + }",
+        ];
+
+        for input in inputs {
+            let (program, tree) = parse_syntax_tree(input);
+            assert!(program.is_err(), "{}", input);
+            assert_eq!(input, tree.to_string());
+        }
+    }
+
+    #[test]
+    fn test_node_kinds() {
+        let (_, tree) = parse_syntax_tree("let x = 1; // one\nreturn x;\nf(x)[0]");
+        let kinds: Vec<SyntaxKind> = tree
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node.kind),
+                SyntaxElement::Token(token) => {
+                    assert!(token.is_trivia());
+                    None
+                }
+            })
+            .collect();
+
+        assert_eq!(
+            vec![
+                SyntaxKind::LetStatement,
+                SyntaxKind::ReturnStatement,
+                SyntaxKind::ExpressionStatement,
+            ],
+            kinds
+        );
+
+        let SyntaxElement::Node(statement) = tree.children.last().unwrap() else {
+            panic!("expected a statement node");
+        };
+        let SyntaxElement::Node(index) = &statement.children[0] else {
+            panic!("expected an expression node");
+        };
+        assert_eq!(SyntaxKind::IndexExpression, index.kind);
+        let SyntaxElement::Node(call) = &index.children[0] else {
+            panic!("expected a call node");
+        };
+        assert_eq!(SyntaxKind::CallExpression, call.kind);
+        assert_eq!("f(x)", call.to_string());
+    }
+
+    #[test]
+    fn test_spans_match_ast() {
+        let input = "let a = 1 + 2; /* c */ fn(x) { x * 2 }(a);\n\
+            let [p, ...q] = [(a), -a] |> f(k: {\"a\": a.b}); // pipe\n\
+            if (a) { x[0] } else if (b) { c ? d : e.m(1) } else { |y| y };\n\
+            for (i in r) { while (i > 0) { i -= 1; break } };\n\
+            match (a) { [1, _] if a || b => \"${a + 1}\", {\"k\": -1} => fn(z = 1, ...w) => z };";
+        let (program, tree) = parse_syntax_tree(input);
+        let Ok(Node::Program(statements)) = program else {
+            panic!("failed to parse {}", input);
+        };
+
+        let mut node_spans = vec![];
+        expression_node_spans(input, &tree, &mut node_spans);
+        let mut expression_spans = vec![];
+        for statement in &statements {
+            statement_expression_spans(statement, &mut expression_spans);
+        }
+
+        assert_eq!(54, expression_spans.len());
+        for span in expression_spans {
+            assert!(
+                node_spans.contains(&span),
+                "no syntax node for {}",
+                &input[span.start..span.end]
+            );
+        }
+
+        let nodes: Vec<&SyntaxNode> = tree
+            .children
+            .iter()
+            .filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            })
+            .collect();
+
+        assert_eq!(statements.len(), nodes.len());
+        for (statement, node) in statements.iter().zip(nodes) {
+            assert_eq!(Some(statement.span), node.span());
+        }
+    }
+
+    #[test]
+    fn test_lower() {
+        // every form of hash pattern key the parser accepts
+        let input = "let {\"s\": a, 1: b, -2: c, true: d} = h; match (h) { {false: _, -0: [e]} => e }";
+        let (_, tree) = parse_syntax_tree(input);
+        let program = lower(input, &tree).unwrap();
+        assert_eq!(
+            "let {\"s\": a, 1: b, -2: c, true: d} = h;match h { {false: _, 0: [e]} => e }",
+            Node::Program(program).to_string()
+        );
+
+        let (_, tree) = parse_syntax_tree("let x = 1;\nlet = 5;");
+        let err = lower("let x = 1;\nlet = 5;", &tree).unwrap_err();
+        assert_eq!("line 2, column 1: Unexpected Error node in the syntax tree", err.to_string());
+
+        let statement = SyntaxNode {
+            kind: SyntaxKind::ExpressionStatement,
+            children: vec![],
+        };
+        let err = lower_expression("", &statement).unwrap_err();
+        assert_eq!(
+            "line 0, column 0: Unexpected ExpressionStatement node in the syntax tree",
+            err.to_string()
+        );
+        let tree = SyntaxNode {
+            kind: SyntaxKind::Program,
+            children: vec![SyntaxElement::Node(statement)],
+        };
+        assert!(lower("", &tree).is_err());
+    }
+
+    // Spans of the nodes in `node` that hold an expression.
+    fn expression_node_spans(source: &str, node: &SyntaxNode, spans: &mut Vec<Span>) {
+        if !matches!(
+            node.kind,
+            SyntaxKind::Program
+                | SyntaxKind::LetStatement
+                | SyntaxKind::ReturnStatement
+                | SyntaxKind::ExpressionStatement
+                | SyntaxKind::BreakStatement
+                | SyntaxKind::ContinueStatement
+                | SyntaxKind::BlockStatement
+                | SyntaxKind::ParameterList
+                | SyntaxKind::Parameter
+                | SyntaxKind::ArgumentList
+                | SyntaxKind::KeywordArgument
+                | SyntaxKind::MatchArm
+                | SyntaxKind::WildcardPattern
+                | SyntaxKind::BindingPattern
+                | SyntaxKind::LiteralPattern
+                | SyntaxKind::ArrayPattern
+                | SyntaxKind::HashPattern
+                | SyntaxKind::RestPattern
+        ) {
+            spans.extend(node.span());
+        }
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(child) => expression_node_spans(source, child, spans),
+                // interpolations are parsed from inside the string token
+                SyntaxElement::Token(SyntaxToken {
+                    token: Token::TEMPLATE(parts),
+                    ..
+                }) => {
+                    for part in parts {
+                        if let StringPart::Expression(span) = part {
+                            let tree = parse_embedded_syntax_tree(source, *span);
+                            expression_node_spans(source, &tree, spans);
+                        }
+                    }
+                }
+                SyntaxElement::Token(_) => {}
+            }
+        }
+    }
+
+    fn statement_expression_spans(statement: &Statement, spans: &mut Vec<Span>) {
+        match &statement.kind {
+            // patterns hold no expressions
+            StatementKind::Let(_, value)
+            | StatementKind::Return(value)
+            | StatementKind::Expression(value) => expression_spans(value, spans),
+            StatementKind::Break | StatementKind::Continue => {}
+        }
+    }
+
+    fn block_expression_spans(block: &BlockStatement, spans: &mut Vec<Span>) {
+        for statement in &block.0 {
+            statement_expression_spans(statement, spans);
+        }
+    }
+
+    // Spans of `expression` and of every expression nested in it.
+    fn expression_spans(expression: &Expression, spans: &mut Vec<Span>) {
+        spans.push(expression.span);
+        let mut children: Vec<&Expression> = vec![];
+        match &expression.kind {
+            ExpressionKind::Identifier(_) | ExpressionKind::Literal(Literal::Integer(_)) => {}
+            ExpressionKind::Literal(Literal::Array(elements)) => children.extend(elements),
+            ExpressionKind::Literal(Literal::Hash(pairs)) => {
+                for (key, value) in pairs {
+                    children.push(key);
+                    children.push(value);
+                }
+            }
+            ExpressionKind::Literal(_) => {}
+            ExpressionKind::Prefix(_, operand) => children.push(operand),
+            ExpressionKind::Infix(left, _, right) | ExpressionKind::Logical(left, _, right) => {
+                children.push(left);
+                children.push(right);
+            }
+            ExpressionKind::Index(left, index) => {
+                children.push(left);
+                children.push(index);
+            }
+            ExpressionKind::IfExpr(condition, consequence, alternative) => {
+                children.push(condition);
+                block_expression_spans(consequence, spans);
+                if let Some(alternative) = alternative {
+                    block_expression_spans(alternative, spans);
+                }
+            }
+            ExpressionKind::While(condition, body) => {
+                children.push(condition);
+                block_expression_spans(body, spans);
+            }
+            ExpressionKind::For(_, _, iterable, body) => {
+                children.push(iterable);
+                block_expression_spans(body, spans);
+            }
+            ExpressionKind::Func(params, body) => {
+                for param in params.iter().flatten() {
+                    if let Parameter::Positional(_, Some(default)) = param {
+                        children.push(default);
+                    }
+                }
+                block_expression_spans(body, spans);
+            }
+            ExpressionKind::FuncCall(function, args, keywords)
+            | ExpressionKind::MethodCall(function, _, args, keywords) => {
+                children.push(function);
+                children.extend(args);
+                children.extend(keywords.iter().map(|(_, value)| value));
+            }
+            ExpressionKind::Template(parts) => {
+                for part in parts {
+                    if let TemplatePart::Expression(part) = part {
+                        children.push(part);
+                    }
+                }
+            }
+            ExpressionKind::Assign(_, _, value) => children.push(value),
+            ExpressionKind::Match(subject, arms) => {
+                children.push(subject);
+                for arm in arms {
+                    children.extend(&arm.guard);
+                    children.push(&arm.body);
+                }
+            }
+            ExpressionKind::Member(object, _) => children.push(object),
+        }
+        for child in children {
+            expression_spans(child, spans);
+        }
+    }
+}
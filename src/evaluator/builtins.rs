@@ -11,7 +11,14 @@ pub enum Builtin {
     Last,
     Rest,
     Push,
-    Put
+    Put,
+    Int,
+    Float,
+    Abs,
+    Floor,
+    Ceil,
+    Round,
+    Range,
 }
 
 impl Builtin {
@@ -23,13 +30,20 @@ impl Builtin {
             "rest" => Object::Builtin(Builtin::Rest),
             "push" => Object::Builtin(Builtin::Push),
             "put" => Object::Builtin(Builtin::Put),
+            "int" => Object::Builtin(Builtin::Int),
+            "float" => Object::Builtin(Builtin::Float),
+            "abs" => Object::Builtin(Builtin::Abs),
+            "floor" => Object::Builtin(Builtin::Floor),
+            "ceil" => Object::Builtin(Builtin::Ceil),
+            "round" => Object::Builtin(Builtin::Round),
+            "range" => Object::Builtin(Builtin::Range),
             _ => return None,
         };
 
         Some(res)
     }
 
-    pub fn apply(&self, args: &Vec<Rc<Object>>) -> Result<Rc<Object>, EvalError> {
+    pub fn apply(&self, args: &[Rc<Object>]) -> Result<Rc<Object>, EvalError> {
         match self {
             Builtin::Len => {
                 if args.len() != 1 {
@@ -53,12 +67,19 @@ impl Builtin {
             Builtin::Last => last(args),
             Builtin::Rest => rest(args),
             Builtin::Push => push(args),
-            Builtin::Put=>put(args)
+            Builtin::Put=>put(args),
+            Builtin::Int => int(args),
+            Builtin::Float => float(args),
+            Builtin::Abs => abs(args),
+            Builtin::Floor => round_with(args, "floor", f64::floor),
+            Builtin::Ceil => round_with(args, "ceil", f64::ceil),
+            Builtin::Round => round_with(args, "round", f64::round),
+            Builtin::Range => range(args),
         }
     }
 }
 
-fn first(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
+fn first(args: &[Rc<Object>])-> Result<Rc<Object>, EvalError>{
     if args.len() != 1 {
         return Err(wrong_argument_number(
             "first",
@@ -70,7 +91,7 @@ fn first(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
     match &*args[0] {
         Object::String(s) => {
             check_container_index(0, s.len())?;
-            let char = s.chars().nth(0).unwrap();
+            let char = s.chars().next().unwrap();
             Ok(Rc::new(Object::String(char.to_string())))
         }
         Object::Array(arr) => {
@@ -85,7 +106,7 @@ fn first(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
     }
 }
 
-fn last(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
+fn last(args: &[Rc<Object>])-> Result<Rc<Object>, EvalError>{
     if args.len() != 1 {
         return Err(wrong_argument_number(
             "last",
@@ -111,7 +132,7 @@ fn last(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
     }
 }
 
-fn rest(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
+fn rest(args: &[Rc<Object>])-> Result<Rc<Object>, EvalError>{
     if args.len() != 1 {
         return Err(wrong_argument_number(
             "rest",
@@ -124,8 +145,8 @@ fn rest(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
         Object::String(s) => Ok(Rc::new(Object::String(s[1..].to_string()))),
         Object::Array(arr) => {
             let mut rest_arr = vec![];
-            for i in 1..arr.len() {
-                let copied_obj = Rc::new((*(arr[i])).clone());
+            for elem in arr.iter().skip(1) {
+                let copied_obj = Rc::new((**elem).clone());
                 rest_arr.push(copied_obj)
             }
             Ok(Rc::new(Object::Array(rest_arr)))
@@ -137,7 +158,7 @@ fn rest(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
     }
 }
 
-fn push(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
+fn push(args: &[Rc<Object>])-> Result<Rc<Object>, EvalError>{
     if args.len() != 2 {
         return Err(wrong_argument_number(
             "push",
@@ -150,8 +171,8 @@ fn push(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
         Object::String(s) => match &*new_elem {
             Object::String(new_elem) => {
                 let mut new_str = s.clone();
-                new_str.push_str(&new_elem);
-                return Ok(Rc::new(Object::String(new_str)))
+                new_str.push_str(new_elem);
+                Ok(Rc::new(Object::String(new_str)))
             }
             _ => Err(operation_unsupported(&new_elem)),
         },
@@ -167,7 +188,7 @@ fn push(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
     }
 }
 
-fn put(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
+fn put(args: &[Rc<Object>])-> Result<Rc<Object>, EvalError>{
    for arg in args{
     println!("{} ", arg)
    };
@@ -175,6 +196,127 @@ fn put(args: &Vec<Rc<Object>>)-> Result<Rc<Object>, EvalError>{
    Ok(access_null())
 }
 
+fn int(args: &[Rc<Object>])-> Result<Rc<Object>, EvalError>{
+    if args.len() != 1 {
+        return Err(wrong_argument_number(
+            "int",
+            1,
+            args.len() as i64,
+        ));
+    }
+
+    match &*args[0] {
+        Object::Integer(_) => Ok(args[0].clone()),
+        Object::Float(x) => {
+            // truncates towards zero; values outside the i64 range are rejected
+            // instead of being saturated
+            if !x.is_finite() || *x < i64::MIN as f64 || *x >= i64::MAX as f64 {
+                return Err(float_to_integer_error(*x));
+            }
+            Ok(Rc::new(Object::Integer(*x as i64)))
+        }
+        Object::String(s) => match s.trim().parse::<i64>() {
+            Ok(i) => Ok(Rc::new(Object::Integer(i))),
+            Err(_) => Err(argument_type_unsupported(args[0].clone(), "int")),
+        },
+        _ => Err(argument_type_unsupported(
+            args[0].clone(),
+            "int",
+        )),
+    }
+}
+
+fn float(args: &[Rc<Object>])-> Result<Rc<Object>, EvalError>{
+    if args.len() != 1 {
+        return Err(wrong_argument_number(
+            "float",
+            1,
+            args.len() as i64,
+        ));
+    }
+
+    match &*args[0] {
+        Object::Integer(i) => Ok(Rc::new(Object::Float(*i as f64))),
+        Object::Float(_) => Ok(args[0].clone()),
+        Object::String(s) => match s.trim().parse::<f64>() {
+            Ok(x) => Ok(Rc::new(Object::Float(x))),
+            Err(_) => Err(argument_type_unsupported(args[0].clone(), "float")),
+        },
+        _ => Err(argument_type_unsupported(
+            args[0].clone(),
+            "float",
+        )),
+    }
+}
+
+fn abs(args: &[Rc<Object>])-> Result<Rc<Object>, EvalError>{
+    if args.len() != 1 {
+        return Err(wrong_argument_number(
+            "abs",
+            1,
+            args.len() as i64,
+        ));
+    }
+
+    match &*args[0] {
+        Object::Integer(i) => Ok(Rc::new(Object::Integer(
+            i.checked_abs().ok_or_else(|| abs_overflow(*i))?,
+        ))),
+        Object::Float(x) => Ok(Rc::new(Object::Float(x.abs()))),
+        _ => Err(argument_type_unsupported(
+            args[0].clone(),
+            "abs",
+        )),
+    }
+}
+
+// floor, ceil and round keep the argument's type: integers are returned as is
+fn round_with(args: &[Rc<Object>], func_name: &str, op: fn(f64) -> f64)-> Result<Rc<Object>, EvalError>{
+    if args.len() != 1 {
+        return Err(wrong_argument_number(
+            func_name,
+            1,
+            args.len() as i64,
+        ));
+    }
+
+    match &*args[0] {
+        Object::Integer(_) => Ok(args[0].clone()),
+        Object::Float(x) => Ok(Rc::new(Object::Float(op(*x)))),
+        _ => Err(argument_type_unsupported(
+            args[0].clone(),
+            func_name,
+        )),
+    }
+}
+
+// range(end), range(start, end) or range(start, end, step), counting up for a
+// positive step and down for a negative one
+fn range(args: &[Rc<Object>])-> Result<Rc<Object>, EvalError>{
+    if args.is_empty() || args.len() > 3 {
+        return Err(argument_number_out_of_range("range", 1, 3, args.len() as i64));
+    }
+
+    let mut bounds = vec![];
+    for arg in args {
+        match &**arg {
+            Object::Integer(i) => bounds.push(*i),
+            _ => return Err(argument_type_unsupported(arg.clone(), "range")),
+        }
+    }
+    let (start, end, step) = match bounds[..] {
+        [end] => (0, end, 1),
+        [start, end] => (start, end, 1),
+        [start, end, step] => (start, end, step),
+        _ => unreachable!(),
+    };
+    if step == 0 {
+        return Err(zero_range_step());
+    }
+
+    Ok(Rc::new(Object::Range(start, end, step)))
+}
+
 impl fmt::Display for Builtin {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -184,6 +326,13 @@ impl fmt::Display for Builtin {
             Builtin::Rest => write!(f, "rest"),
             Builtin::Push => write!(f, "push"),
             Builtin::Put => write!(f, "put"),
+            Builtin::Int => write!(f, "int"),
+            Builtin::Float => write!(f, "float"),
+            Builtin::Abs => write!(f, "abs"),
+            Builtin::Floor => write!(f, "floor"),
+            Builtin::Ceil => write!(f, "ceil"),
+            Builtin::Round => write!(f, "round"),
+            Builtin::Range => write!(f, "range"),
         }
     }
 }
@@ -1,36 +1,94 @@
-use crate::{object::Object, token::Token};
+use crate::{
+    ast::{InfixOperator, Pattern, PrefixOperator},
+    object::Object,
+    token::Span,
+};
 use std::{fmt, rc::Rc};
-pub struct EvalError(String);
+pub struct EvalError {
+    message: String,
+    span: Option<Span>,
+    signal: Option<Signal>,
+}
+
+/// Control flow leaving a loop body early, carried on the error path so it
+/// passes through any expression the `break` or `continue` is nested in
+/// until the enclosing loop catches it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Signal {
+    Break,
+    Continue,
+}
+
+impl EvalError {
+    fn new(message: String) -> Self {
+        Self {
+            message,
+            span: None,
+            signal: None,
+        }
+    }
+
+    pub fn signal(&self) -> Option<Signal> {
+        self.signal
+    }
+
+    /// Turns a `break` or `continue` into a plain error, so that it stops
+    /// at the function call it would otherwise leave.
+    pub fn without_signal(mut self) -> Self {
+        self.signal = None;
+        self
+    }
+
+    /// Attaches the location of the failing node, unless a more precise
+    /// location was already recorded further down the tree.
+    pub fn with_span(mut self, span: Span) -> Self {
+        self.span.get_or_insert(span);
+        self
+    }
+}
 
 impl fmt::Display for EvalError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        return write!(f, "{}", self.0);
+        match &self.span {
+            Some(span) => write!(f, "{}: {}", span, self.message),
+            None => write!(f, "{}", self.message),
+        }
     }
 }
 
 
-pub fn prefix_error(t: &Token, o: &Object) -> EvalError {
-    EvalError(format!("Unknown Operator:  {}{}", t, o))
+// The parser only accepts `break` and `continue` inside a loop of the same
+// function, and a call turns a signal leaving its function into a plain
+// error, so the message is never shown unless a signal escapes anyway.
+pub fn loop_signal(signal: Signal) -> EvalError {
+    let keyword = match signal {
+        Signal::Break => "break",
+        Signal::Continue => "continue",
+    };
+    EvalError {
+        signal: Some(signal),
+        ..EvalError::new(format!("{} outside of a loop", keyword))
+    }
 }
 
-pub fn minus_prefix_error(o: &Object) -> EvalError {
-    EvalError(format!("Unknown Operator: -{}",  o))
+pub fn prefix_error(t: &PrefixOperator, o: &Object) -> EvalError {
+    EvalError::new(format!("Unknown Operator: {}{}", t, o))
 }
 
-pub fn infix_error<T: fmt::Display>(left: T, t: &Token, right: T) -> EvalError {
-    EvalError(format!("Unknown Operator: {} {} {}", left, t, right))
+pub fn infix_error<T: fmt::Display>(left: T, t: &InfixOperator, right: T) -> EvalError {
+    EvalError::new(format!("Unknown Operator: {} {} {}", left, t, right))
 }
 
-pub fn type_mismatch(left: &str, t: &Token, right: &str) -> EvalError {
-    EvalError(format!("Type Mismatch:  {} {} {}", left, t, right))
+pub fn type_mismatch(left: &str, t: &InfixOperator, right: &str) -> EvalError {
+    EvalError::new(format!("Type Mismatch: {} {} {}", left, t, right))
 }
 
 pub fn not_a_function(func: Rc<Object>) -> EvalError {
-    EvalError(format!("{} is not a function", func))
+    EvalError::new(format!("{} is not a function", func))
 }
 
 pub fn argument_type_unsupported(obj: Rc<Object>, func_name: &str) -> EvalError {
-    EvalError(format!(
+    EvalError::new(format!(
         "Argument {} of type {} is not supported by function {}",
         obj,
         obj.get_type(),
@@ -39,31 +97,115 @@ pub fn argument_type_unsupported(obj: Rc<Object>, func_name: &str) -> EvalError
 }
 
 pub fn wrong_argument_number(func_name: &str, expect: i64, actual: i64) -> EvalError {
-    EvalError(format!(
+    EvalError::new(format!(
         "Function {} expected {} arguments, but got {}",
         func_name, expect, actual
     ))
 }
 
+// `max` is `None` for a function taking any number of arguments.
+pub fn wrong_arity(min: usize, max: Option<usize>, actual: usize) -> EvalError {
+    let expected = match max {
+        Some(max) if max == min => format!("{}", min),
+        Some(max) => format!("{} to {}", min, max),
+        None => format!("at least {}", min),
+    };
+    EvalError::new(format!(
+        "Function expected {} arguments, but got {}",
+        expected, actual
+    ))
+}
+
+pub fn missing_argument(param: &str) -> EvalError {
+    EvalError::new(format!("Missing argument for parameter {}", param))
+}
+
+pub fn unknown_keyword_argument(name: &str) -> EvalError {
+    EvalError::new(format!("Unknown keyword argument {}", name))
+}
+
+pub fn duplicate_argument(param: &str) -> EvalError {
+    EvalError::new(format!("Multiple values for parameter {}", param))
+}
+
+pub fn keyword_arguments_unsupported(func: &Object) -> EvalError {
+    EvalError::new(format!("{} does not take keyword arguments", func))
+}
+
+pub fn argument_number_out_of_range(func_name: &str, min: i64, max: i64, actual: i64) -> EvalError {
+    EvalError::new(format!(
+        "Function {} expected {} to {} arguments, but got {}",
+        func_name, min, max, actual
+    ))
+}
+
+pub fn zero_range_step() -> EvalError {
+    EvalError::new("range step cannot be zero".to_string())
+}
+
+pub fn not_iterable(obj: &Object) -> EvalError {
+    EvalError::new(format!("{} is not iterable", obj.get_type()))
+}
+
+pub fn no_match_arm(obj: &Object) -> EvalError {
+    EvalError::new(format!("No match arm matched {}", obj.get_type()))
+}
+
+pub fn destructure_mismatch(pattern: &Pattern, obj: &Object) -> EvalError {
+    EvalError::new(format!("Cannot destructure {} with {}", obj.get_type(), pattern))
+}
+
 pub fn operation_unsupported(obj: &Object) -> EvalError {
-    EvalError(format!(
+    EvalError::new(format!(
         "Object of type {} is not supported by this operation",
         obj.get_type()
     ))
 }
 
+pub fn division_by_zero() -> EvalError {
+    EvalError::new("Division by zero".to_string())
+}
+
+pub fn integer_overflow(left: i64, t: &InfixOperator, right: i64) -> EvalError {
+    EvalError::new(format!("Integer overflow: {} {} {}", left, t, right))
+}
+
+pub fn negation_overflow(i: i64) -> EvalError {
+    EvalError::new(format!("Integer overflow: -({})", i))
+}
+
+pub fn abs_overflow(i: i64) -> EvalError {
+    EvalError::new(format!("Integer overflow: abs({})", i))
+}
+
+pub fn shift_out_of_range(amount: i64) -> EvalError {
+    EvalError::new(format!("Shift amount {} is outside of 0..63", amount))
+}
+
 pub fn identifier_unfound(id: &str) -> EvalError {
-    EvalError(format!("Identifer not Found:  {}", id))
+    EvalError::new(format!("Identifer not Found: {}", id))
+}
+
+pub fn undefined_assignment(id: &str) -> EvalError {
+    EvalError::new(format!("Cannot assign to undefined variable: {}", id))
 }
 
 pub fn array_index_invalid(index: i64) -> EvalError {
-    EvalError(format!("index {} is invalid", index))
+    EvalError::new(format!("index {} is invalid", index))
 }
 
 pub fn array_index_out_of_bound(index: i64) -> EvalError {
-    EvalError(format!("index {} exceeds the container length", index))
+    EvalError::new(format!("index {} exceeds the container length", index))
+}
+
+pub fn no_member(obj: &Object, key: &str) -> EvalError {
+    EvalError::new(format!("{} has no member {}", obj.get_type(), key))
 }
 
 pub fn unhashable_expression(obj: &Object) -> EvalError {
-    EvalError(format!("{} cannot be used as a hashkey", obj))
+    EvalError::new(format!("{} cannot be used as a hashkey", obj))
+}
+
+pub fn float_to_integer_error(x: f64) -> EvalError {
+    EvalError::new(format!("Float {:?} cannot be converted to an integer", x))
 }
@@ -2,15 +2,19 @@ pub mod builtins;
 mod error;
 use std::{
     cell::RefCell,
-    collections::{BTreeMap, HashMap},
+    cmp::Ordering,
+    collections::HashMap,
     rc::Rc,
 };
 
 use crate::{
-    ast::{BlockStatement, Expression, Literal, Node, Statement},
+    ast::{
+        BlockStatement, Expression, ExpressionKind, InfixOperator, Literal, LogicalOperator,
+        MatchArm, Node, Parameter, Pattern, PatternKind, PrefixOperator, Statement, StatementKind,
+        TemplatePart,
+    },
     object::environment::{Env, Environment},
     object::Object,
-    token::*,
 };
 
 use self::{builtins::Builtin, error::*};
@@ -21,12 +25,10 @@ thread_local!(static NULL:Rc<Object> = Rc::new(Object::Null));
 pub fn eval(node: Node, env: Env) -> Result<Rc<Object>, EvalError> {
     match node {
         Node::Program(p) => eval_program(&p, env),
-        Node::Stat(s) => eval_statements(&s, env),
-        Node::Expr(e) => eval_expression(&e, env),
     }
 }
 
-fn eval_program(p: &Vec<Statement>, env: Env) -> Result<Rc<Object>, EvalError> {
+fn eval_program(p: &[Statement], env: Env) -> Result<Rc<Object>, EvalError> {
     let mut res = access_null();
     for stmt in p {
         res = eval_statements(stmt, env.clone())?;
@@ -39,18 +41,27 @@ fn eval_program(p: &Vec<Statement>, env: Env) -> Result<Rc<Object>, EvalError> {
 }
 
 fn eval_statements(s: &Statement, env: Env) -> Result<Rc<Object>, EvalError> {
-    match s {
-        Statement::Expression(expr) => eval_expression(expr, env.clone()),
-        Statement::Return(expr) => {
-            let expr = eval_expression(&expr, env.clone())?;
-            return Ok(Rc::new(Object::ReturnValue(expr)));
+    match &s.kind {
+        StatementKind::Expression(expr) => eval_expression(expr, env.clone()),
+        StatementKind::Return(expr) => {
+            let expr = eval_expression(expr, env.clone())?;
+            Ok(Rc::new(Object::ReturnValue(expr)))
         }
-        Statement::Let(identifier, expr) => {
-            let value = eval_expression(&expr, env.clone())?;
-            env.borrow_mut().set(identifier, value.clone());
+        StatementKind::Let(pattern, expr) => {
+            let value = eval_expression(expr, env.clone())?;
+            // nothing is bound unless the whole pattern matches
+            let mut bindings = vec![];
+            if !match_pattern(pattern, &value, &mut bindings) {
+                return Err(destructure_mismatch(pattern, &value).with_span(pattern.span));
+            }
+            for (name, obj) in bindings {
+                env.borrow_mut().set(&name, obj);
+            }
 
             Ok(value)
         }
+        StatementKind::Break => Err(loop_signal(Signal::Break)),
+        StatementKind::Continue => Err(loop_signal(Signal::Continue)),
     }
 }
 
@@ -58,7 +69,7 @@ fn eval_block_statements(statements: &BlockStatement, env: Env) -> Result<Rc<Obj
     let mut res = access_null();
 
     for s in &statements.0 {
-        res = eval_statements(&s, env.clone())?;
+        res = eval_statements(s, env.clone())?;
 
         if let Object::ReturnValue(_) = *res {
             return Ok(res);
@@ -69,41 +80,111 @@ fn eval_block_statements(statements: &BlockStatement, env: Env) -> Result<Rc<Obj
 }
 
 fn eval_expression(e: &Expression, env: Env) -> Result<Rc<Object>, EvalError> {
+    eval_expression_kind(&e.kind, env).map_err(|err| err.with_span(e.span))
+}
+
+fn eval_expression_kind(e: &ExpressionKind, env: Env) -> Result<Rc<Object>, EvalError> {
     match e {
-        Expression::Identifier(id) => eval_identifier(id, env.clone()),
-        Expression::Literal(lit) => eval_literal(lit, env.clone()),
-        Expression::Prefix(operator, expr) => {
+        ExpressionKind::Identifier(id) => eval_identifier(id, env.clone()),
+        ExpressionKind::Literal(lit) => eval_literal(lit, env.clone()),
+        ExpressionKind::Prefix(operator, expr) => {
             let right = eval_expression(expr, env.clone())?;
-            return eval_prefix_expression(&operator, right.clone());
+            eval_prefix_expression(operator, right.clone())
         }
-        Expression::Infix(left, operator, right) => {
+        ExpressionKind::Infix(left, operator, right) => {
             let left = eval_expression(left, env.clone())?;
             let right = eval_expression(right, env.clone())?;
-            return eval_infix_expression(left.clone(), operator, right.clone());
+            eval_infix_expression(left.clone(), operator, right.clone())
+        }
+        ExpressionKind::Logical(left, operator, right) => {
+            let left = eval_expression(left, env.clone())?;
+            match (operator, is_truthy(&left)) {
+                (LogicalOperator::And, false) | (LogicalOperator::Or, true) => Ok(left),
+                _ => eval_expression(right, env.clone()),
+            }
+        }
+        ExpressionKind::IfExpr(condition, consequence, alternative) => {
+            eval_if_expression(condition, consequence, alternative, env.clone())
         }
-        Expression::IfExpr(condition, consequence, alternative) => {
-            return eval_if_expression(condition, consequence, alternative, env.clone());
+        ExpressionKind::While(condition, body) => eval_while_expression(condition, body, env),
+        ExpressionKind::For(first, second, iterable, body) => {
+            eval_for_expression(first, second, iterable, body, env)
         }
-        Expression::Func(params, body) => {
+        ExpressionKind::Func(params, body) => {
             let params = params.clone();
             let body = body.clone();
 
             Ok(Rc::new(Object::Funtion(params, body, env.clone())))
         }
-        Expression::FuncCall(expr, params) => {
+        ExpressionKind::FuncCall(expr, params, keywords) => {
             let func = eval_expression(expr, env.clone())?;
             let args = eval_expressions(params, env.clone())?;
-            apply_function(func, &args)
+            let keywords = eval_keyword_arguments(keywords, env.clone())?;
+            apply_function(func, &args, &keywords)
+        }
+        ExpressionKind::MethodCall(receiver, name, params, keywords) => {
+            let receiver = eval_expression(receiver, env.clone())?;
+            let func = eval_identifier(name, env.clone())?;
+            let mut args = vec![receiver];
+            args.extend(eval_expressions(params, env.clone())?);
+            let keywords = eval_keyword_arguments(keywords, env.clone())?;
+            apply_function(func, &args, &keywords)
+        }
+        ExpressionKind::Member(object, key) => {
+            let object = eval_expression(object, env.clone())?;
+            eval_member_expression(object, key)
         }
-        Expression::Index(left, index) => {
+        ExpressionKind::Index(left, index) => {
             let left = eval_expression(left, env.clone())?;
             let index = eval_expression(index, env.clone())?;
             eval_index_expression(left, index)
         }
+        ExpressionKind::Template(parts) => eval_template(parts, env),
+        ExpressionKind::Assign(name, operator, value) => {
+            eval_assign_expression(name, operator, value, env)
+        }
+        ExpressionKind::Match(subject, arms) => eval_match_expression(subject, arms, env),
+    }
+}
+
+fn eval_assign_expression(
+    name: &str,
+    operator: &Option<InfixOperator>,
+    value: &Expression,
+    env: Env,
+) -> Result<Rc<Object>, EvalError> {
+    let mut value = eval_expression(value, env.clone())?;
+    if let Some(operator) = operator {
+        let current = env
+            .borrow()
+            .get(name)
+            .ok_or_else(|| undefined_assignment(name))?;
+        value = eval_infix_expression(current, operator, value)?;
+    }
+
+    if env.borrow_mut().assign(name, value.clone()) {
+        Ok(value)
+    } else {
+        Err(undefined_assignment(name))
     }
 }
 
-fn eval_expressions(expressions: &Vec<Expression>, env: Env) -> Result<Vec<Rc<Object>>, EvalError> {
+fn eval_template(parts: &[TemplatePart], env: Env) -> Result<Rc<Object>, EvalError> {
+    let mut res = String::new();
+    for part in parts {
+        match part {
+            TemplatePart::Text(text) => res.push_str(text),
+            TemplatePart::Expression(expr) => {
+                let value = eval_expression(expr, env.clone())?;
+                res.push_str(&value.to_string());
+            }
+        }
+    }
+
+    Ok(Rc::new(Object::String(res)))
+}
+
+fn eval_expressions(expressions: &[Expression], env: Env) -> Result<Vec<Rc<Object>>, EvalError> {
     let mut exprs = vec![];
     for expr in expressions {
         let res = eval_expression(expr, env.clone())?;
@@ -113,9 +194,23 @@ fn eval_expressions(expressions: &Vec<Expression>, env: Env) -> Result<Vec<Rc<Ob
     Ok(exprs)
 }
 
+fn eval_keyword_arguments(
+    keywords: &[(String, Expression)],
+    env: Env,
+) -> Result<Vec<(String, Rc<Object>)>, EvalError> {
+    let mut res = vec![];
+    for (name, value) in keywords {
+        res.push((name.clone(), eval_expression(value, env.clone())?));
+    }
+
+    Ok(res)
+}
+
+#[allow(clippy::mutable_key_type)]
 fn eval_literal(lit: &Literal, env: Env) -> Result<Rc<Object>, EvalError> {
     match lit {
         Literal::Integer(i) => Ok(Rc::new(Object::Integer(*i))),
+        Literal::Float(x) => Ok(Rc::new(Object::Float(*x))),
         Literal::Bool(b) => Ok(match_boolean_expression(b)),
         Literal::String(s) => Ok(Rc::new(Object::String(s.to_string()))),
         Literal::Array(elems) => {
@@ -139,11 +234,14 @@ fn eval_identifier(id: &str, env: Env) -> Result<Rc<Object>, EvalError> {
     }
 }
 
-fn eval_prefix_expression(operator: &Token, right: Rc<Object>) -> Result<Rc<Object>, EvalError> {
+fn eval_prefix_expression(operator: &PrefixOperator, right: Rc<Object>) -> Result<Rc<Object>, EvalError> {
     match operator {
-        Token::BANG => eval_bang_operator_expression(right.clone()),
-        Token::MINUS => eval_minus_prefix_operation(right.clone()),
-        _ => Err(prefix_error(operator, &right)),
+        PrefixOperator::Bang => eval_bang_operator_expression(right.clone()),
+        PrefixOperator::Minus => eval_minus_prefix_operation(right.clone()),
+        PrefixOperator::Tilde => match *right {
+            Object::Integer(i) => Ok(Rc::new(Object::Integer(!i))),
+            _ => Err(prefix_error(operator, &right)),
+        },
     }
 }
 
@@ -159,7 +257,7 @@ fn eval_index_expression(left: Rc<Object>, index: Rc<Object>) -> Result<Rc<Objec
         Ok(Rc::new(Object::String(char.to_string())))
     }else if let Object::Hash(map) = left{
         if !index.is_hashtable(){
-            return  Err(unhashable_expression(&index));
+            return  Err(unhashable_expression(index));
         }
 
         match map.get(index){
@@ -167,7 +265,18 @@ fn eval_index_expression(left: Rc<Object>, index: Rc<Object>) -> Result<Rc<Objec
             None=>Ok(access_null())
         }
     }else{
-        return  Err(operation_unsupported(&index));
+        Err(operation_unsupported(index))
+    }
+}
+
+// A missing key gives `NULL`, as with indexing.
+fn eval_member_expression(object: Rc<Object>, key: &str) -> Result<Rc<Object>, EvalError> {
+    match &*object {
+        Object::Hash(map) => match map.get(&Object::String(key.to_string())) {
+            Some(val) => Ok(val.clone()),
+            None => Ok(access_null()),
+        },
+        _ => Err(no_member(&object, key)),
     }
 }
 
@@ -175,24 +284,28 @@ fn eval_bang_operator_expression(expr: Rc<Object>) -> Result<Rc<Object>, EvalErr
     match *expr {
         Object::Boolean(b) => Ok(match_boolean_expression(&(!b))),
         Object::Integer(i) => {
-            let b = if i == 0 { true } else { false };
+            let b = i == 0;
 
-            return Ok(match_boolean_expression(&b));
+            Ok(match_boolean_expression(&b))
         }
+        Object::Float(x) => Ok(match_boolean_expression(&(x == 0.0))),
         _ => Ok(match_boolean_expression(&false)),
     }
 }
 
 fn eval_minus_prefix_operation(expr: Rc<Object>) -> Result<Rc<Object>, EvalError> {
     match *expr {
-        Object::Integer(i) => Ok(Rc::new(Object::Integer(-i))),
-        _ => Err(minus_prefix_error(&expr)),
+        Object::Integer(i) => Ok(Rc::new(Object::Integer(
+            i.checked_neg().ok_or_else(|| negation_overflow(i))?,
+        ))),
+        Object::Float(x) => Ok(Rc::new(Object::Float(-x))),
+        _ => Err(prefix_error(&PrefixOperator::Minus, &expr)),
     }
 }
 
 fn eval_infix_expression(
     left_expr: Rc<Object>,
-    operator: &Token,
+    operator: &InfixOperator,
     right_expr: Rc<Object>,
 ) -> Result<Rc<Object>, EvalError> {
     let left_val = &*left_expr;
@@ -201,6 +314,16 @@ fn eval_infix_expression(
         (Object::Integer(left), Object::Integer(right)) => {
             eval_integer_infix_expression(*left, operator, *right)
         }
+        (Object::Float(left), Object::Float(right)) => {
+            eval_float_infix_expression(*left, operator, *right)
+        }
+        // mixed arithmetic promotes the integer operand to a float
+        (Object::Integer(left), Object::Float(right)) => {
+            eval_float_infix_expression(*left as f64, operator, *right)
+        }
+        (Object::Float(left), Object::Integer(right)) => {
+            eval_float_infix_expression(*left, operator, *right as f64)
+        }
         (Object::Boolean(left), Object::Boolean(right)) => {
             eval_boolean_infix_expression(*left, operator, *right)
         }
@@ -213,21 +336,86 @@ fn eval_infix_expression(
     }
 }
 
+// Integer division and `%` truncate towards zero, so `a % b` takes the sign of
+// `a` and `(a / b) * b + a % b == a`. A negative exponent gives a float.
+// `>>` is an arithmetic shift, keeping the sign.
 fn eval_integer_infix_expression(
     left: i64,
-    operator: &Token,
+    operator: &InfixOperator,
     right: i64,
 ) -> Result<Rc<Object>, EvalError> {
     let res = match *operator {
-        Token::PLUS => Object::Integer(left + right),
-        Token::MINUS => Object::Integer(left - right),
-        Token::ASTERISK => Object::Integer(left * right),
-        Token::SLASH => Object::Integer(left / right),
-        Token::GT => return Ok(match_boolean_expression(&(left > right))),
-        Token::LT => return Ok(match_boolean_expression(&(left < right))),
-        Token::EQ => return Ok(match_boolean_expression(&(left == right))),
-        Token::NOTEQ => return Ok(match_boolean_expression(&(left != right))),
-        _ => return Err(infix_error(&left, operator, &right)),
+        InfixOperator::Plus | InfixOperator::Minus | InfixOperator::Asterisk => {
+            let res = match *operator {
+                InfixOperator::Plus => left.checked_add(right),
+                InfixOperator::Minus => left.checked_sub(right),
+                _ => left.checked_mul(right),
+            };
+            Object::Integer(res.ok_or_else(|| integer_overflow(left, operator, right))?)
+        }
+        InfixOperator::Slash | InfixOperator::Percent if right == 0 => {
+            return Err(division_by_zero())
+        }
+        InfixOperator::Slash => Object::Integer(
+            left.checked_div(right)
+                .ok_or_else(|| integer_overflow(left, operator, right))?,
+        ),
+        InfixOperator::Percent => Object::Integer(left.wrapping_rem(right)),
+        InfixOperator::Power if right < 0 => Object::Float((left as f64).powf(right as f64)),
+        // 0, 1 and -1 stay in range for exponents too large for `checked_pow`
+        InfixOperator::Power if (-1..=1).contains(&left) => Object::Integer(match (left, right) {
+            (-1, _) if right % 2 == 0 => 1,
+            (0, 0) => 1,
+            _ => left,
+        }),
+        InfixOperator::Power => Object::Integer(
+            u32::try_from(right)
+                .ok()
+                .and_then(|exp| left.checked_pow(exp))
+                .ok_or_else(|| integer_overflow(left, operator, right))?,
+        ),
+        InfixOperator::Gt => return Ok(match_boolean_expression(&(left > right))),
+        InfixOperator::Lt => return Ok(match_boolean_expression(&(left < right))),
+        InfixOperator::GtEq => return Ok(match_boolean_expression(&(left >= right))),
+        InfixOperator::LtEq => return Ok(match_boolean_expression(&(left <= right))),
+        InfixOperator::Eq => return Ok(match_boolean_expression(&(left == right))),
+        InfixOperator::NotEq => return Ok(match_boolean_expression(&(left != right))),
+        InfixOperator::BitAnd => Object::Integer(left & right),
+        InfixOperator::BitOr => Object::Integer(left | right),
+        InfixOperator::BitXor => Object::Integer(left ^ right),
+        InfixOperator::Shl | InfixOperator::Shr if !(0..=63).contains(&right) => {
+            return Err(shift_out_of_range(right))
+        }
+        InfixOperator::Shl => Object::Integer(left << right),
+        InfixOperator::Shr => Object::Integer(left >> right),
+    };
+
+    Ok(Rc::new(res))
+}
+
+fn eval_float_infix_expression(
+    left: f64,
+    operator: &InfixOperator,
+    right: f64,
+) -> Result<Rc<Object>, EvalError> {
+    let res = match *operator {
+        InfixOperator::Plus => Object::Float(left + right),
+        InfixOperator::Minus => Object::Float(left - right),
+        InfixOperator::Asterisk => Object::Float(left * right),
+        InfixOperator::Slash => Object::Float(left / right),
+        InfixOperator::Percent => Object::Float(left % right),
+        InfixOperator::Power => Object::Float(left.powf(right)),
+        InfixOperator::Gt => return Ok(match_boolean_expression(&(left > right))),
+        InfixOperator::Lt => return Ok(match_boolean_expression(&(left < right))),
+        InfixOperator::GtEq => return Ok(match_boolean_expression(&(left >= right))),
+        InfixOperator::LtEq => return Ok(match_boolean_expression(&(left <= right))),
+        InfixOperator::Eq => return Ok(match_boolean_expression(&(left == right))),
+        InfixOperator::NotEq => return Ok(match_boolean_expression(&(left != right))),
+        InfixOperator::BitAnd
+        | InfixOperator::BitOr
+        | InfixOperator::BitXor
+        | InfixOperator::Shl
+        | InfixOperator::Shr => return Err(infix_error(left, operator, right)),
     };
 
     Ok(Rc::new(res))
@@ -235,24 +423,24 @@ fn eval_integer_infix_expression(
 
 fn eval_boolean_infix_expression(
     left: bool,
-    operator: &Token,
+    operator: &InfixOperator,
     right: bool,
 ) -> Result<Rc<Object>, EvalError> {
     match *operator {
-        Token::EQ => return Ok(match_boolean_expression(&(left == right))),
-        Token::NOTEQ => return Ok(match_boolean_expression(&(left != right))),
+        InfixOperator::Eq => Ok(match_boolean_expression(&(left == right))),
+        InfixOperator::NotEq => Ok(match_boolean_expression(&(left != right))),
 
-        _ => return Err(infix_error(left, operator, right)),
-    };
+        _ => Err(infix_error(left, operator, right)),
+    }
 }
 
 fn eval_string_infix_expression(
     s1: &str,
-    operator: &Token,
+    operator: &InfixOperator,
     s2: &str,
 ) -> Result<Rc<Object>, EvalError> {
     match operator {
-        Token::PLUS => Ok(Rc::new(Object::String(s1.to_string() + s2))),
+        InfixOperator::Plus => Ok(Rc::new(Object::String(s1.to_string() + s2))),
         _ => Err(infix_error(s1, operator, s2)),
     }
 }
@@ -265,17 +453,109 @@ fn eval_if_expression(
 ) -> Result<Rc<Object>, EvalError> {
     let condition = eval_expression(condition, env.clone())?;
     if is_truthy(&condition) {
-        return eval_block_statements(consequence, env.clone());
+        eval_block_statements(consequence, env.clone())
     } else {
         match &alternative {
-            Some(alter) => eval_block_statements(&alter, env.clone()),
+            Some(alter) => eval_block_statements(alter, env.clone()),
             None => Ok(access_null()),
         }
     }
 }
 
+// Arms are tried in order. The names bound by an arm's pattern are only
+// visible in its guard and body.
+fn eval_match_expression(
+    subject: &Expression,
+    arms: &[MatchArm],
+    env: Env,
+) -> Result<Rc<Object>, EvalError> {
+    let value = eval_expression(subject, env.clone())?;
+    for arm in arms {
+        let mut bindings = vec![];
+        if !match_pattern(&arm.pattern, &value, &mut bindings) {
+            continue;
+        }
+
+        let mut arm_env = Environment::new_enclosed_environment(env.clone());
+        for (name, obj) in bindings {
+            arm_env.set(&name, obj);
+        }
+        let arm_env = Rc::new(RefCell::new(arm_env));
+        if let Some(guard) = &arm.guard {
+            if !is_truthy(&*eval_expression(guard, arm_env.clone())?) {
+                continue;
+            }
+        }
+
+        return eval_expression(&arm.body, arm_env);
+    }
+
+    Err(no_match_arm(&value))
+}
+
+// Tests `value` against `pattern`, collecting the names it binds. Numeric
+// literals compare like `==`, so `1` matches `1.0`.
+fn match_pattern(
+    pattern: &Pattern,
+    value: &Rc<Object>,
+    bindings: &mut Vec<(String, Rc<Object>)>,
+) -> bool {
+    match (&pattern.kind, &**value) {
+        (PatternKind::Wildcard, _) => true,
+        (PatternKind::Binding(name), _) => {
+            bindings.push((name.clone(), value.clone()));
+            true
+        }
+        (PatternKind::Literal(literal), value) => match (literal, value) {
+            (Literal::Integer(a), Object::Integer(b)) => a == b,
+            (Literal::Integer(a), Object::Float(b)) => *a as f64 == *b,
+            (Literal::Float(a), Object::Integer(b)) => *a == *b as f64,
+            (Literal::Float(a), Object::Float(b)) => a == b,
+            (Literal::Bool(a), Object::Boolean(b)) => a == b,
+            (Literal::String(a), Object::String(b)) => a == b,
+            _ => false,
+        },
+        (PatternKind::Array(patterns, rest), Object::Array(elems)) => {
+            let length_matches = match rest {
+                Some(_) => elems.len() >= patterns.len(),
+                None => elems.len() == patterns.len(),
+            };
+            if !length_matches
+                || !patterns
+                    .iter()
+                    .zip(elems)
+                    .all(|(pattern, elem)| match_pattern(pattern, elem, bindings))
+            {
+                return false;
+            }
+
+            match rest {
+                Some(rest) => {
+                    let rest_value = Rc::new(Object::Array(elems[patterns.len()..].to_vec()));
+                    match_pattern(rest, &rest_value, bindings)
+                }
+                None => true,
+            }
+        }
+        (PatternKind::Hash(pairs), Object::Hash(map)) => pairs.iter().all(|(key, pattern)| {
+            let key = match key {
+                Literal::Integer(i) => Object::Integer(*i),
+                Literal::Bool(b) => Object::Boolean(*b),
+                Literal::String(s) => Object::String(s.clone()),
+                _ => return false,
+            };
+            match map.get(&key) {
+                Some(value) => match_pattern(pattern, value, bindings),
+                None => false,
+            }
+        }),
+        _ => false,
+    }
+}
+
+#[allow(clippy::mutable_key_type)]
 fn eval_hash_literal(
-    map: &BTreeMap<Expression, Expression>,
+    map: &[(Expression, Expression)],
     env: Env,
 ) -> Result<HashMap<Rc<Object>, Rc<Object>>, EvalError> {
     let mut final_map = HashMap::new();
@@ -284,7 +564,7 @@ fn eval_hash_literal(
         let key = eval_expression(k, env.clone())?;
 
         if !key.is_hashtable() {
-            return Err(unhashable_expression(&*key));
+            return Err(unhashable_expression(&key));
         }
 
         let value = eval_expression(v, env.clone())?;
@@ -295,30 +575,81 @@ fn eval_hash_literal(
     Ok(final_map)
 }
 
-fn apply_function(func: Rc<Object>, args: &Vec<Rc<Object>>) -> Result<Rc<Object>, EvalError> {
+fn apply_function(
+    func: Rc<Object>,
+    args: &[Rc<Object>],
+    keywords: &[(String, Rc<Object>)],
+) -> Result<Rc<Object>, EvalError> {
     match &*func {
         Object::Funtion(params, body, env) => {
-            // let extended_env = Rc::new(RefCell::new(extend_func_env(&env, args, params)));
-            let extended_env = match params {
-                Some(params) => Rc::new(RefCell::new(extend_func_env(env.clone(), args, params))),
-                None => env.clone(),
-            };
-            let evluated = eval_block_statements(&body, extended_env)?;
+            // every call gets its own scope, so its bindings never leak into
+            // the one the function was defined in
+            let params = params.as_deref().unwrap_or_default();
+            // a `break` or `continue` never leaves the function it is in
+            let extended_env = extend_func_env(env.clone(), params, args, keywords)
+                .map_err(EvalError::without_signal)?;
+            let evluated = eval_block_statements(body, extended_env).map_err(EvalError::without_signal)?;
 
             unwrap_return_value(evluated)
         }
+        Object::Builtin(_) if !keywords.is_empty() => Err(keyword_arguments_unsupported(&func)),
         Object::Builtin(builtin) => builtin.apply(args),
         _ => Err(not_a_function(func)),
     }
 }
 
-fn extend_func_env(outer_env: Env, args: &Vec<Rc<Object>>, params: &Vec<String>) -> Environment {
-    let mut env = Environment::new_enclosed_environment(outer_env.clone());
-    for (i, param) in params.iter().enumerate() {
-        env.set(param, args[i].clone())
+// Positional arguments fill the parameters in order, leaving any extra ones
+// to the rest parameter, and keyword arguments fill them by name. Defaults
+// are evaluated in the new scope for whatever is still unset, so a default
+// can refer to the parameters before it.
+fn extend_func_env(
+    outer_env: Env,
+    params: &[Parameter],
+    args: &[Rc<Object>],
+    keywords: &[(String, Rc<Object>)],
+) -> Result<Env, EvalError> {
+    let positional = params
+        .iter()
+        .filter(|param| matches!(param, Parameter::Positional(..)))
+        .count();
+    let required = params
+        .iter()
+        .filter(|param| matches!(param, Parameter::Positional(_, None)))
+        .count();
+    let max = (positional == params.len()).then_some(positional);
+    if max.is_some_and(|max| args.len() > max) || (keywords.is_empty() && args.len() < required) {
+        return Err(wrong_arity(required, max, args.len()));
+    }
+
+    let mut values: Vec<Option<Rc<Object>>> = vec![None; params.len()];
+    for (value, arg) in values.iter_mut().zip(&args[..args.len().min(positional)]) {
+        *value = Some(arg.clone());
+    }
+    for (name, arg) in keywords {
+        let index = params
+            .iter()
+            .position(|param| matches!(param, Parameter::Positional(param, _) if param == name))
+            .ok_or_else(|| unknown_keyword_argument(name))?;
+        if values[index].is_some() {
+            return Err(duplicate_argument(name));
+        }
+        values[index] = Some(arg.clone());
+    }
+
+    let env = Rc::new(RefCell::new(Environment::new_enclosed_environment(outer_env)));
+    for (param, value) in params.iter().zip(values) {
+        let value = match (param, value) {
+            (_, Some(value)) => value,
+            (Parameter::Positional(_, Some(default)), None) => eval_expression(default, env.clone())?,
+            (Parameter::Positional(name, None), None) => return Err(missing_argument(name)),
+            (Parameter::Rest(_), None) => {
+                Rc::new(Object::Array(args.get(positional..).unwrap_or_default().to_vec()))
+            }
+        };
+        env.borrow_mut().set(param.name(), value);
     }
 
-    env
+    Ok(env)
 }
 
 fn unwrap_return_value(obj: Rc<Object>) -> Result<Rc<Object>, EvalError> {
@@ -328,10 +659,129 @@ fn unwrap_return_value(obj: Rc<Object>) -> Result<Rc<Object>, EvalError> {
     }
 }
 
+fn eval_while_expression(
+    condition: &Expression,
+    body: &BlockStatement,
+    env: Env,
+) -> Result<Rc<Object>, EvalError> {
+    while is_truthy(&*eval_expression(condition, env.clone())?) {
+        if let Some(res) = eval_loop_body(body, env.clone())? {
+            return Ok(res);
+        }
+    }
+
+    Ok(access_null())
+}
+
+// Loops over the elements of arrays, the characters of strings and the values
+// of ranges, binding the index too when given two variables, and over the keys,
+// or keys and values, of hashes.
+fn eval_for_expression(
+    first: &str,
+    second: &Option<String>,
+    iterable: &Expression,
+    body: &BlockStatement,
+    env: Env,
+) -> Result<Rc<Object>, EvalError> {
+    let items = eval_expression(iterable, env.clone())?;
+    let by_key = matches!(*items, Object::Hash(_));
+    for (key, value) in iterate(&items).map_err(|err| err.with_span(iterable.span))? {
+        // each pass binds the loop variables in its own scope, leaving any
+        // outer variable of the same name untouched
+        let mut loop_env = Environment::new_enclosed_environment(env.clone());
+        match second {
+            Some(second) => {
+                loop_env.set(first, key);
+                loop_env.set(second, value);
+            }
+            None if by_key => loop_env.set(first, key),
+            None => loop_env.set(first, value),
+        }
+        if let Some(res) = eval_loop_body(body, Rc::new(RefCell::new(loop_env)))? {
+            return Ok(res);
+        }
+    }
+
+    Ok(access_null())
+}
+
+type Items = Box<dyn Iterator<Item = (Rc<Object>, Rc<Object>)>>;
+
+// Key and value of each item of an iterable object, with indices as the keys
+// of sequences. Arrays, strings and hashes are iterated over a snapshot taken
+// up front, ranges lazily. Hashes are walked in key order, see `compare_keys`.
+fn iterate(obj: &Object) -> Result<Items, EvalError> {
+    let items: Items = match obj {
+        Object::Array(elems) => Box::new(elems.clone().into_iter().enumerate().map(|(i, elem)| {
+            (Rc::new(Object::Integer(i as i64)), elem)
+        })),
+        Object::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            Box::new(chars.into_iter().enumerate().map(|(i, ch)| {
+                (
+                    Rc::new(Object::Integer(i as i64)),
+                    Rc::new(Object::String(ch.to_string())),
+                )
+            }))
+        }
+        Object::Hash(map) => {
+            let mut entries: Vec<(Rc<Object>, Rc<Object>)> = map.clone().into_iter().collect();
+            entries.sort_by(|(a, _), (b, _)| compare_keys(a, b));
+            Box::new(entries.into_iter())
+        }
+        Object::Range(start, end, step) => {
+            let (end, step) = (*end, *step);
+            let values = std::iter::successors(Some(*start), move |i| i.checked_add(step))
+                .take_while(move |i| if step > 0 { *i < end } else { *i > end });
+            Box::new(values.enumerate().map(|(i, value)| {
+                (Rc::new(Object::Integer(i as i64)), Rc::new(Object::Integer(value)))
+            }))
+        }
+        _ => return Err(not_iterable(obj)),
+    };
+
+    Ok(items)
+}
+
+// Order of hash keys: booleans, then integers, then strings, each sorted by
+// value.
+fn compare_keys(a: &Object, b: &Object) -> Ordering {
+    fn rank(key: &Object) -> u8 {
+        match key {
+            Object::Boolean(_) => 0,
+            Object::Integer(_) => 1,
+            _ => 2,
+        }
+    }
+
+    match (a, b) {
+        (Object::Boolean(a), Object::Boolean(b)) => a.cmp(b),
+        (Object::Integer(a), Object::Integer(b)) => a.cmp(b),
+        (Object::String(a), Object::String(b)) => a.cmp(b),
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+// Runs one pass of a loop body, returning the value to leave the loop with
+// after a `break` or `return`.
+fn eval_loop_body(body: &BlockStatement, env: Env) -> Result<Option<Rc<Object>>, EvalError> {
+    match eval_block_statements(body, env) {
+        Ok(res) => match *res {
+            Object::ReturnValue(_) => Ok(Some(res)),
+            _ => Ok(None),
+        },
+        Err(err) => match err.signal() {
+            Some(Signal::Break) => Ok(Some(access_null())),
+            Some(Signal::Continue) => Ok(None),
+            None => Err(err),
+        },
+    }
+}
+
 fn is_truthy(obj: &Object) -> bool {
     match obj {
-        Object::Null | Object::Boolean(false) | Object::Integer(0) => false,
-        _ => true,
+        Object::Float(x) => *x != 0.0,
+        _ => !matches!(obj, Object::Null | Object::Boolean(false) | Object::Integer(0)),
     }
 }
 
@@ -400,6 +850,58 @@ mod tests {
         test_helper(&tests)
     }
 
+    #[test]
+    fn test_eval_float_expression() {
+        let tests = [
+            ("3.14", "3.14"),
+            ("-2.5", "-2.5"),
+            ("1.0", "1.0"),
+            ("1e-9", "1e-9"),
+            ("1e20", "1e20"),
+            ("0.1 + 0.2", "0.30000000000000004"),
+            ("1 / 2", "0"),
+            ("1 / 2.0", "0.5"),
+            ("2 * 1.5", "3.0"),
+            ("10 - 0.5", "9.5"),
+            ("1.0 / 0", "inf"),
+            ("(1 + 2 + 3) / 3.0", "2.0"),
+            ("1 == 1.0", "true"),
+            ("2.5 > 2", "true"),
+            ("1 < 0.5", "false"),
+            ("1.5 != 1.5", "false"),
+            ("!0.0", "true"),
+            ("if (0.0) { 1 } else { 2 }", "2"),
+            ("1.5 + true", "line 1, column 1: Type Mismatch: Float(1.5) + Boolean(true)"),
+        ];
+
+        test_helper(&tests)
+    }
+
+    #[test]
+    fn test_float_builtins() {
+        let tests = [
+            ("float(3)", "3.0"),
+            ("float(\"2.5\")", "2.5"),
+            ("int(3.99)", "3"),
+            ("int(-3.99)", "-3"),
+            ("int(\"42\")", "42"),
+            ("abs(-2.5)", "2.5"),
+            ("abs(-3)", "3"),
+            ("abs(-9223372036854775807 - 1)", "line 1, column 1: Integer overflow: abs(-9223372036854775808)"),
+            ("floor(2.7)", "2.0"),
+            ("ceil(2.1)", "3.0"),
+            ("round(2.5)", "3.0"),
+            ("round(4)", "4"),
+            ("int(1e300)", "line 1, column 1: Float 1e300 cannot be converted to an integer"),
+            (
+                "float(\"abc\")",
+                "line 1, column 1: Argument abc of type String(\"abc\") is not supported by function float",
+            ),
+        ];
+
+        test_helper(&tests)
+    }
+
     #[test]
     fn test_bang_operator() {
         let tests = [
@@ -422,25 +924,454 @@ mod tests {
 
         test_helper(&tests_non_null);
         test_helper(&tests_null);
+
+        let tests_else_if = [
+            ("let sign = fn(n) { if (n < 0) { -1 } else if (n == 0) { 0 } else { 1 } }; sign(-5)", "-1"),
+            ("sign(0)", "0"),
+            ("sign(7)", "1"),
+            ("if (false) { 1 } else if (false) { 2 }", "NULL"),
+        ];
+        test_helper(&tests_else_if);
+    }
+
+    #[test]
+    fn test_match_expressions() {
+        let tests = [
+            ("match (2) { 1 => \"one\", 2 => \"two\", _ => \"many\" }", "two"),
+            ("match (7) { 1 => \"one\", _ => \"many\" }", "many"),
+            ("match (-1) { -1 => \"minus one\", _ => \"other\" }", "minus one"),
+            ("match (2.0) { 2 => \"whole\", _ => \"other\" }", "whole"),
+            ("match (\"1\") { 1 => \"int\", \"1\" => \"string\" }", "string"),
+            ("match (false) { true => 1, false => 0 }", "0"),
+            ("match (5 * 2) { n => n + 1 }", "11"),
+            (
+                "let describe = fn(xs) { match (xs) { [] => \"empty\", [x] => \"one: ${x}\", [a, b] => \"pair\", [first, ...rest] => \"${first} and ${len(rest)} more\" } }; describe([])",
+                "empty",
+            ),
+            ("describe([9])", "one: 9"),
+            ("describe([1, 2])", "pair"),
+            ("describe([1, 2, 3, 4])", "1 and 3 more"),
+            ("match ([1, 2, 3]) { [_, ...tail] => tail }", "[2, 3]"),
+            ("match ([1]) { [_, ...tail] => tail }", "[]"),
+            ("match ([1, [2, 3]]) { [a, [b, c]] => a + b + c }", "6"),
+            (
+                "let handle = fn(ev) { match (ev) { {\"type\": \"user\", \"id\": id} => \"user ${id}\", {\"type\": t} => \"other ${t}\", _ => \"unknown\" } }; handle({\"type\": \"user\", \"id\": 42, \"extra\": true})",
+                "user 42",
+            ),
+            ("handle({\"type\": \"admin\"})", "other admin"),
+            ("handle([1])", "unknown"),
+            ("match ({1: \"a\", true: \"b\"}) { {1: x, true: y} => x + y }", "ab"),
+            (
+                "let sign_of = fn(n) { match (n) { x if x > 0 => \"positive\", x if x < 0 => \"negative\", _ => \"zero\" } }; sign_of(-3)",
+                "negative",
+            ),
+            ("sign_of(0)", "zero"),
+            ("let bound = 1; match (2) { bound => bound }; bound", "1"),
+            ("let seen = 0; match ([4, 5]) { [a, b] => seen = a + b }; seen", "9"),
+            ("match (5) { 1 => 1, x if x > 10 => 2 }", "line 1, column 1: No match arm matched Integer(5)"),
+            ("match ([1, 2]) { [a] => a }", "line 1, column 1: No match arm matched Array[1, 2]"),
+            ("match (1) { x => x + true }", "line 1, column 18: Type Mismatch: Integer(1) + Boolean(true)"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_destructuring_let() {
+        let tests = [
+            ("let [d1, d2, ...d_rest] = [1, 2, 3, 4]; d1 + d2", "3"),
+            ("d_rest", "[3, 4]"),
+            ("let [_, [n1, n2]] = [0, [5, 6]]; n1 * n2", "30"),
+            (
+                "let person = {\"name\": \"Ada\", \"age\": 36, \"lang\": \"en\"}; let {\"name\": p_name, \"age\": p_age} = person; \"${p_name} ${p_age}\"",
+                "Ada 36",
+            ),
+            ("let [only] = [7]", "[7]"),
+            ("let [head, ...] = \"not an array\"", "line 1, column 5: Cannot destructure String(\"not an array\") with [head, ..._]"),
+            ("let [s1, s2] = [1, 2, 3]", "line 1, column 5: Cannot destructure Array[1, 2, 3] with [s1, s2]"),
+            ("let {\"missing\": m} = {\"other\": 1}", "line 1, column 5: Cannot destructure Map{other: 1} with {\"missing\": m}"),
+            // a failed destructuring binds nothing
+            ("let [partial, 0] = [1, 2]; partial", "line 1, column 5: Cannot destructure Array[1, 2] with [partial, 0]"),
+            ("partial", "line 1, column 1: Identifer not Found: partial"),
+            ("let swap = fn(pair) { let [l, r] = pair; [r, l] }; swap([1, 2])", "[2, 1]"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_function_parameters() {
+        let tests = [
+            ("let scaled = fn(x, by = 10) { x * by }; scaled(2)", "20"),
+            ("scaled(2, 3)", "6"),
+            ("let span_of = fn(lo = 1, hi = lo + 4) { hi - lo }; span_of(2)", "4"),
+            ("span_of(hi: 3)", "2"),
+            ("let count_rest = fn(first, ...others) { [first, others] }; count_rest(1)", "[1, []]"),
+            ("count_rest(1, 2, 3)", "[1, [2, 3]]"),
+            ("let sub = fn(x, y) { x - y }; sub(y: 2, x: 10)", "8"),
+            ("sub(10, y: 2)", "8"),
+            ("let opts = fn(v, ...more) { [v, more] }; opts(v: 4)", "[4, []]"),
+            ("sub(1)", "line 1, column 1: Function expected 2 arguments, but got 1"),
+            ("sub(y: 1)", "line 1, column 1: Missing argument for parameter x"),
+            ("sub(1, 2, 3)", "line 1, column 1: Function expected 2 arguments, but got 3"),
+            ("sub(1, z: 2)", "line 1, column 1: Unknown keyword argument z"),
+            ("sub(1, x: 2)", "line 1, column 1: Multiple values for parameter x"),
+            ("sub(x: 1, x: 2)", "line 1, column 1: Multiple values for parameter x"),
+            ("count_rest(1, others: [2])", "line 1, column 1: Unknown keyword argument others"),
+            ("len(x: \"abc\")", "line 1, column 1: Builtin Function: len does not take keyword arguments"),
+            ("let bad_default = fn(x = missing_name) { x }; bad_default()", "line 1, column 26: Identifer not Found: missing_name"),
+            ("bad_default(5)", "5"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_function_scopes_and_arity() {
+        let tests = [
+            ("let leak_check = fn() { let inner = 1; inner }; leak_check()", "1"),
+            ("inner", "line 1, column 1: Identifer not Found: inner"),
+            ("let shadowed = 5; let shadow = fn() { let shadowed = 6; shadowed }; shadow(); shadowed", "5"),
+            ("let tally = 0; let bump = fn() { tally += 1 }; bump(); bump(); tally", "2"),
+            ("let counter = fn() { let c = 0; fn() { c += 1 } }; let next = counter(); next(); next()", "2"),
+            ("leak_check(1)", "line 1, column 1: Function expected 0 arguments, but got 1"),
+            ("let pair_up = fn(x, y) { [x, y] }; pair_up(1)", "line 1, column 36: Function expected 2 arguments, but got 1"),
+            ("pair_up()", "line 1, column 1: Function expected 2 arguments, but got 0"),
+            ("pair_up(1, 2, 3)", "line 1, column 1: Function expected 2 arguments, but got 3"),
+            ("let ranged = fn(x, y = 1) { x + y }; ranged()", "line 1, column 38: Function expected 1 to 2 arguments, but got 0"),
+            ("ranged(1, 2, 3)", "line 1, column 1: Function expected 1 to 2 arguments, but got 3"),
+            ("let at_least = fn(x, ...xs) { x }; at_least()", "line 1, column 36: Function expected at least 1 arguments, but got 0"),
+            ("at_least(1, 2, 3)", "1"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_lambdas() {
+        let tests = [
+            ("let double = |x| x * 2; double(21)", "42"),
+            ("let add_arrow = fn(a, b) => a + b; add_arrow(1, 2)", "3"),
+            ("let apply_twice = |f, x| f(f(x)); apply_twice(|n| n + 3, 1)", "7"),
+            ("let adder = |a| |b| a + b; adder(2)(5)", "7"),
+            ("let answer = || 42; answer()", "42"),
+            ("let offset = |x, by = 10| x + by; offset(1)", "11"),
+            ("offset(1, by: 2)", "3"),
+            ("let sign_word = |n| match (n) { 0 => \"zero\", n if n < 0 => \"neg\", _ => \"pos\" }; sign_word(-2)", "neg"),
+            ("double(1, 2)", "line 1, column 1: Function expected 1 arguments, but got 2"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_method_calls() {
+        let tests = [
+            ("[1, 2, 3].rest().push(4).len()", "3"),
+            ("len(push(rest([1, 2, 3]), 4)) == [1, 2, 3].rest().push(4).len()", "true"),
+            ("\"hello\".len()", "5"),
+            ("-2.5.abs().floor()", "-2.0"),
+            ("let triple = |x| x * 3; 4.triple()", "12"),
+            ("let between = fn(x, lo, hi = 10) { x >= lo && x <= hi }; 5.between(1)", "true"),
+            ("20.between(1, hi: 30)", "true"),
+            ("let profile = {\"name\": \"Ada\", \"tags\": [\"math\"], \"nested\": {\"depth\": 2}}; profile.name", "Ada"),
+            ("profile.tags.first()", "math"),
+            ("profile.nested.depth + 1", "3"),
+            ("profile.missing", "NULL"),
+            ("[1].first", "line 1, column 1: Array[1] has no member first"),
+            ("5.name", "line 1, column 1: Integer(5) has no member name"),
+            ("5.nothing_here()", "line 1, column 1: Identifer not Found: nothing_here"),
+            ("1.len()", "line 1, column 1: Argument 1 of type Integer(1) is not supported by function len"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_pipelines() {
+        let tests = [
+            ("let keep = fn(xs, pred) { let out = []; for (x in xs) { if (pred(x)) { out = push(out, x) } }; out }; keep([1, 2], |x| x > 1)", "[2]"),
+            ("let each = fn(xs, f) { let out = []; for (x in xs) { out = push(out, f(x)) }; out }; each([1, 2], |x| x + 1)", "[2, 3]"),
+            ("[1, -2, 3, -4, 5] |> keep(|x| x > 0) |> each(|x| x * 10)", "[10, 30, 50]"),
+            ("[1, -2, 3, -4, 5] |> keep(|x| x > 0) |> len()", "3"),
+            ("\"abc\" |> len", "3"),
+            ("-7 |> abs() |> float()", "7.0"),
+            ("let minus = |a, b| a - b; 10 |> minus(3)", "7"),
+            ("10 |> minus(b: 4)", "6"),
+            ("5 |> (|x| x * x)", "25"),
+            ("2 |> 3", "line 1, column 1: 3 is not a function"),
+            ("[1] |> minus()", "line 1, column 1: Function expected 2 arguments, but got 1"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_conditional_expressions() {
+        let tests = [
+            ("1 < 2 ? \"yes\" : \"no\"", "yes"),
+            ("0 ? \"yes\" : \"no\"", "no"),
+            ("let grade = fn(n) { n >= 90 ? \"A\" : n >= 80 ? \"B\" : \"C\" }; grade(85)", "B"),
+            ("let picked = 0; true ? picked : undefined_name", "0"),
+            ("let hits = 0; false ? hits += 1 : (hits += 10); hits", "10"),
+            ("true ? 1 + true : 2", "line 1, column 8: Type Mismatch: Integer(1) + Boolean(true)"),
+        ];
+
+        test_helper(&tests);
     }
 
     #[test]
     fn test_error_handling() {
         let tests = [
-            ("1+true", "Type Mismatch: Integer(1) + Boolean(true)"),
-            ("false+2", "Type Mismatch: Boolean(false) + Integer(2)"),
-            ("-true", "Unknown Operator: -true"),
-            ("true + false", "Unknown Operator: true + false"),
-            ("5; true + false; 5", "Unknown Operator: true + false"),
+            ("1+true", "line 1, column 1: Type Mismatch: Integer(1) + Boolean(true)"),
+            ("false+2", "line 1, column 1: Type Mismatch: Boolean(false) + Integer(2)"),
+            ("-true", "line 1, column 1: Unknown Operator: -true"),
+            ("true + false", "line 1, column 1: Unknown Operator: true + false"),
+            ("5; true + false; 5", "line 1, column 4: Unknown Operator: true + false"),
             (
                 "if (10 > 1) { true + false; }",
-                "Unknown Operator: true + false",
+                "line 1, column 15: Unknown Operator: true + false",
             ),
             (
                 "if (10 > 1) {if (10 > 1) {return true + false;}return 1;}",
-                "Unknown Operator: true + false",
+                "line 1, column 34: Unknown Operator: true + false",
+            ),
+            ("ggg", "line 1, column 1: Identifer not Found: ggg"),
+            (
+                "let f = fn(x) {\n  x + missing\n};\nf(1)",
+                "line 2, column 7: Identifer not Found: missing",
+            ),
+            ("len(1, 2)", "line 1, column 1: Function len expected 1 arguments, but got 2"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let tests = [
+            (
+                "let name = \"monkey\"; let items = [1, 2, 3]; \"hello ${name}, you have ${len(items)} items\"",
+                "hello monkey, you have 3 items",
+            ),
+            ("\"${1.5 * 2} ${true} ${[1, \"a\"]} ${\"x\" + \"y\"}\"", "3.0 true [1, a] xy"),
+            ("\"cost: \\${price}\"", "cost: ${price}"),
+            ("let n = 2; \"n=${\"${n * n}\"}\"", "n=4"),
+            ("\"value: ${missing}\"", "line 1, column 11: Identifer not Found: missing"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_raw_and_multiline_strings() {
+        let tests = [
+            (r##"let q = r#"{"name": "${name}"}"#; q"##, r#"{"name": "${name}"}"#),
+            ("let sql = \"\"\"\n    SELECT *\n      FROM users\n    \"\"\"; len(sql)", "21"),
+            ("let t = \"\"\"\n  a\\tb\n  \"\"\"; t + r\"\\n\"", "a\tb\\n"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_assignment() {
+        let tests = [
+            ("let x = 1; x = 2; x", "2"),
+            ("let x = 1; let y = x = 5; x + y", "10"),
+            ("let a = 10; a += 5; a -= 3; a *= 2; a /= 4; a", "6"),
+            ("let s = \"ab\"; s += \"cd\"; s", "abcd"),
+            ("let f = 1.5; f *= 2; f", "3.0"),
+            (
+                "let counter = fn() { let n = 0; fn() { n += 1 } }; let c = counter(); c(); c(); c()",
+                "3",
+            ),
+            ("let x = 1; let set = fn() { x = 42; }; set(); x", "42"),
+            ("let x = 1; let shadow = fn(v) { let x = v; x = 3; x }; shadow(2) * 10 + x", "31"),
+            ("nope = 1", "line 1, column 1: Cannot assign to undefined variable: nope"),
+            ("let z = 1;\nw += z", "line 2, column 1: Cannot assign to undefined variable: w"),
+            ("let t = true; t += 1", "line 1, column 15: Type Mismatch: Boolean(true) + Integer(1)"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_while_loops() {
+        let tests = [
+            ("let i = 0; let sum = 0; while (i < 5) { i += 1; sum += i; } sum", "15"),
+            ("let i = 0; while (i < 3) { i += 1 }", "NULL"),
+            ("let i = 0; while (true) { i += 1; if (i > 6) { break; } } i", "7"),
+            (
+                "let i = 0; let odd = 0; while (i < 10) { i += 1; if (i / 2 * 2 == i) { continue; } odd += 1; } odd",
+                "5",
+            ),
+            (
+                "let i = 0; let n = 0; while (i < 3) { i += 1; let j = 0; while (true) { j += 1; n += 1; if (j == 2) { break } } } n",
+                "6",
+            ),
+            (
+                "let find = fn(arr) { let i = 0; while (i < len(arr)) { if (arr[i] > 2) { return arr[i]; } i += 1; }; -1 }; find([1, 5, 3])",
+                "5",
+            ),
+            ("let i = 0; while (i < 100000) { i += 1 } i", "100000"),
+            ("while (missing) { 1 }", "line 1, column 8: Identifer not Found: missing"),
+            // `break` and `continue` leave the loop from inside any expression
+            ("let n = 0; while (n < 3) { n += 1; let y = [if (true) { break }]; }; n", "1"),
+            (
+                "let after = 0; for (i in [1, 2]) { put(if (true) { continue } else { 0 }); after += 1 }; after",
+                "0",
+            ),
+            ("let m = 0; while (m < 5) { m += 1; let z = 1 + if (m == 2) { break } else { 0 }; }; m", "2"),
+            ("let q = 0; for (i in range(4)) { q += match (i) { 1 => if (true) { continue } else { 0 }, _ => i } }; q", "5"),
+            ("let stop = 0; for (i in range(9)) { stop = i; \"${if (i == 3) { break } else { i }}\" }; stop", "3"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_for_loops() {
+        let tests = [
+            ("let sum = 0; for (x in [1, 2, 3]) { sum += x } sum", "6"),
+            ("let acc = \"\"; for (i, x in [\"a\", \"b\"]) { acc += \"${i}${x}\" } acc", "0a1b"),
+            ("let out = \"\"; for (c in \"héllo\") { out = c + out } out", "olléh"),
+            ("let total = 0; for (k in {1: 10, 2: 20}) { total += k } total", "3"),
+            ("let total = 0; for (k, v in {1: 10, 2: 20}) { total += k * v } total", "50"),
+            ("let sum = 0; for (i in range(5)) { sum += i } sum", "10"),
+            ("let s = \"\"; for (i in range(10, 0, -3)) { s += \"${i} \" } s", "10 7 4 1 "),
+            ("let n = 0; for (i in range(3, 3)) { n += 1 } n", "0"),
+            ("let n = 0; for (i in range(100000)) { n += 1 } n", "100000"),
+            (
+                "let hit = -1; for (i, x in [5, 7, 9]) { if (x == 7) { hit = i; break; } } hit",
+                "1",
+            ),
+            (
+                "let odd = 0; for (i in range(10)) { if (i / 2 * 2 == i) { continue } odd += 1 } odd",
+                "5",
+            ),
+            (
+                "let find = fn(xs, t) { for (i, x in xs) { if (x == t) { return i } }; -1 }; find([3, 4], 4)",
+                "1",
             ),
-            ("ggg", "Identifer not Found: ggg"),
+            ("for (x in [1]) { 1 }", "NULL"),
+            (
+                "let keys = \"\"; for (k in {\"c\": 3, \"a\": 1, \"e\": 5, \"b\": 2, \"d\": 4}) { keys += k }; keys",
+                "abcde",
+            ),
+            (
+                "let order = []; for (k, v in {\"x\": 1, 10: 2, true: 3, -4: 4, false: 5}) { order = push(order, k) }; order",
+                "[false, true, -4, 10, x]",
+            ),
+            ("let x = 10; for (x in [1, 2]) { }; x", "10"),
+            ("let i = \"keep\"; let f = fn() { i }; for (i in range(3)) { }; f()", "keep"),
+            ("let k = 0; let v = 0; for (k, v in [7, 8]) { }; [k, v]", "[0, 0]"),
+            ("let fns = []; for (n in [1, 2]) { fns = push(fns, || n) }; fns[0]() + fns[1]()", "3"),
+            ("range(1, 10, 2)", "range(1, 10, 2)"),
+            ("for (x in 5) { x }", "line 1, column 11: Integer(5) is not iterable"),
+            ("range(0, 5, 0)", "line 1, column 1: range step cannot be zero"),
+            ("range()", "line 1, column 1: Function range expected 1 to 3 arguments, but got 0"),
+            (
+                "range(1.5)",
+                "line 1, column 1: Argument 1.5 of type Float(1.5) is not supported by function range",
+            ),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let tests = [
+            ("true && false", "false"),
+            ("true || false", "true"),
+            ("1 && \"yes\"", "yes"),
+            ("0 && missing", "0"),
+            ("\"name\" || missing", "name"),
+            ("0 || 0.0 || \"fallback\"", "fallback"),
+            ("let calls = 0; let f = fn(v) { calls += 1; v }; f(false) && f(true); f(true) || f(false); calls", "2"),
+            ("let x = 3; x > 1 && x < 5", "true"),
+            ("if (1 < 2 && 2 < 1 || true) { \"taken\" }", "taken"),
+            ("true && missing", "line 1, column 9: Identifer not Found: missing"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_comparison_and_arithmetic_operators() {
+        let tests = [
+            ("3 <= 3", "true"),
+            ("4 <= 3", "false"),
+            ("3 >= 4", "false"),
+            ("2.5 >= 2", "true"),
+            ("7 % 3", "1"),
+            ("9223372036854775807 + 1", "line 1, column 1: Integer overflow: 9223372036854775807 + 1"),
+            ("-9223372036854775807 - 2", "line 1, column 1: Integer overflow: -9223372036854775807 - 2"),
+            ("2 ** 62 * 4", "line 1, column 1: Integer overflow: 4611686018427387904 * 4"),
+            ("let big = 9223372036854775807; big += 1", "line 1, column 32: Integer overflow: 9223372036854775807 + 1"),
+            ("big", "9223372036854775807"),
+            ("let smallest = -9223372036854775807 - 1; smallest", "-9223372036854775808"),
+            ("-smallest", "line 1, column 1: Integer overflow: -(-9223372036854775808)"),
+            ("9223372036854775807 + -9223372036854775807", "0"),
+            ("-7 % 3", "-1"),
+            ("7 % -3", "1"),
+            ("-7 / 2 * 2 + -7 % 2", "-7"),
+            ("7.5 % 2", "1.5"),
+            ("2 ** 10", "1024"),
+            ("2 ** 3 ** 2", "512"),
+            ("-2 ** 2", "-4"),
+            ("(-2) ** 3", "-8"),
+            ("2 ** -2", "0.25"),
+            ("2 ** 0.5 * 2 ** 0.5", "2.0000000000000004"),
+            ("0 ** 0", "1"),
+            ("1 / 0", "line 1, column 1: Division by zero"),
+            ("let x = 0;\n5 % x", "line 2, column 1: Division by zero"),
+            ("1.0 / 0", "inf"),
+            ("2 ** 63", "line 1, column 1: Integer overflow: 2 ** 63"),
+            ("1 ** 4294967296", "1"),
+            ("0 ** 4294967296", "0"),
+            ("(-1) ** 4294967296", "1"),
+            ("(-1) ** 4294967297", "-1"),
+            ("2 ** 4294967296", "line 1, column 1: Integer overflow: 2 ** 4294967296"),
+            ("true <= false", "line 1, column 1: Unknown Operator: true <= false"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let tests = [
+            ("12 & 10", "8"),
+            ("12 | 10", "14"),
+            ("12 ^ 10", "6"),
+            ("~0", "-1"),
+            ("~5", "-6"),
+            ("1 << 62", "4611686018427387904"),
+            ("1 << 63", "-9223372036854775808"),
+            ("-16 >> 2", "-4"),
+            ("0xF0 >> 4 | 0b1 << 8", "271"),
+            (
+                "let flags = 0b0110; flags & 0b0100 != 0",
+                "line 1, column 21: Type Mismatch: Integer(6) & Boolean(true)",
+            ),
+            ("let flags = 0b0110; (flags & 0b0100) != 0", "true"),
+            ("1 << 64", "line 1, column 1: Shift amount 64 is outside of 0..63"),
+            ("8 >> -1", "line 1, column 1: Shift amount -1 is outside of 0..63"),
+            ("1.5 & 1", "line 1, column 1: Unknown Operator: 1.5 & 1"),
+            ("true | false", "line 1, column 1: Unknown Operator: true | false"),
+            ("~true", "line 1, column 1: Unknown Operator: ~true"),
+        ];
+
+        test_helper(&tests);
+    }
+
+    #[test]
+    fn test_unicode_identifiers() {
+        let tests = [
+            ("let row2 = 2; let user_id_v3 = row2 * 3; user_id_v3", "6"),
+            ("let größe = 1.5; let 名前 = \"monkey\"; 名前", "monkey"),
         ];
 
         test_helper(&tests);
@@ -458,7 +1389,7 @@ mod tests {
 
     #[test]
     fn test_array() {
-        let tests = [("[1,2]", "[1,2]")];
+        let tests = [("[1,2]", "[1, 2]")];
 
         test_helper(&tests);
     }
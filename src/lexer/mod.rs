@@ -1,52 +1,176 @@
-use crate::token::{self, Token};
+use std::borrow::Cow;
+
+use crate::token::{self, Span, SpannedToken, StringPart, Token};
 const ZERO_NULL: char = 0 as char;
-pub struct Lexer {
-    input: Vec<char>,
+
+/// Lexer over a borrowed source string.
+///
+/// Identifiers and escape-free strings are slices of the source, so lexing
+/// does not allocate per token. Besides `next_token`, which keeps returning
+/// `Token::EOF` once the input is exhausted, the lexer is an `Iterator` that
+/// yields every token up to (but not including) `EOF`. Whitespace and
+/// comments are skipped unless the lexer is built `with_trivia`.
+pub struct Lexer<'a> {
+    input: &'a str,
+    // byte offsets of `ch` and of the character after it
     position: usize,
     read_position: usize,
     ch: char,
+    // line and column of `ch`
+    line: usize,
+    column: usize,
+    keep_trivia: bool,
 }
 
-impl Lexer {
-    pub fn new(input: &str) -> Self {
-        let input = input.chars().collect();
+impl<'a> Lexer<'a> {
+    pub fn new(input: &'a str) -> Self {
         let mut lex = Self {
-            input: input,
+            input,
             position: 0,
             read_position: 0,
             ch: ZERO_NULL,
+            line: 1,
+            column: 1,
+            keep_trivia: false,
+        };
+        lex.read_char();
+        lex
+    }
+
+    /// Emits whitespace and comments as `Token::WHITESPACE` and
+    /// `Token::COMMENT` instead of skipping them.
+    pub fn with_trivia(mut self) -> Self {
+        self.keep_trivia = true;
+        self
+    }
+
+    /// Lexer over the expression of a `${...}` interpolation, whose source
+    /// `span` was reported in a `Token::TEMPLATE`. Token spans stay relative
+    /// to the whole `input`.
+    pub fn embedded(input: &'a str, span: Span) -> Self {
+        let mut lex = Self {
+            input: &input[..span.end],
+            position: span.start,
+            read_position: span.start,
+            ch: ZERO_NULL,
+            line: span.line,
+            column: span.column,
+            keep_trivia: false,
         };
         lex.read_char();
+        lex.line = span.line;
+        lex.column = span.column;
         lex
     }
 
+    pub fn source(&self) -> &'a str {
+        self.input
+    }
+
     fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = ZERO_NULL;
-        } else {
-            self.ch = self.input[self.read_position]
+        if self.read_position > 0 && self.position < self.input.len() {
+            if self.ch == '\n' {
+                self.line += 1;
+                self.column = 1;
+            } else {
+                self.column += 1;
+            }
         }
 
         self.position = self.read_position;
-        self.read_position += 1;
+        match self.input[self.read_position..].chars().next() {
+            Some(ch) => {
+                self.ch = ch;
+                self.read_position += ch.len_utf8();
+            }
+            None => self.ch = ZERO_NULL,
+        }
     }
 
     fn peek_char(&self) -> char {
-        if self.read_position >= self.input.len() {
-            ZERO_NULL
-        } else {
-            self.input[self.read_position]
+        self.peek_char_at(0)
+    }
+
+    // Looks `n` characters past the next one without consuming anything.
+    fn peek_char_at(&self, n: usize) -> char {
+        self.input
+            .get(self.read_position..)
+            .and_then(|rest| rest.chars().nth(n))
+            .unwrap_or(ZERO_NULL)
+    }
+
+    pub fn next_token(&mut self) -> SpannedToken<'a> {
+        loop {
+            let start = self.mark();
+            let token = match (self.ch, self.peek_char()) {
+                (' ' | '\t' | '\n' | '\r', _) => {
+                    self.skip_whitespace();
+                    Token::WHITESPACE
+                }
+                ('/', '/') => {
+                    self.skip_line_comment();
+                    Token::COMMENT
+                }
+                ('/', '*') => match self.skip_block_comment() {
+                    Ok(()) => Token::COMMENT,
+                    Err(message) => Token::ERROR(message),
+                },
+                ('"', '"') if self.peek_char_at(1) == '"' => match self.read_multiline_string() {
+                    Ok(token) => token,
+                    Err(error) => return error,
+                },
+                ('r', '"' | '#') if self.at_raw_string() => match self.read_raw_string() {
+                    Ok(token) => token,
+                    Err(message) => Token::ERROR(message),
+                },
+                ('"', _) => match self.read_string() {
+                    Ok(token) => token,
+                    Err(error) => return error,
+                },
+                _ => self.read_token(),
+            };
+            if !self.keep_trivia && matches!(token, Token::WHITESPACE | Token::COMMENT) {
+                continue;
+            }
+
+            return SpannedToken {
+                token,
+                span: self.span_from(start),
+            };
         }
     }
 
-    pub fn next_token(&mut self) -> token::Token {
-        self.skip_whitespace();
+    /// Zero-width span at the current character.
+    fn mark(&self) -> Span {
+        Span {
+            start: self.position,
+            end: self.position,
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn span_from(&self, start: Span) -> Span {
+        Span {
+            end: self.position,
+            ..start
+        }
+    }
+
+    fn slice_from(&self, start: usize) -> &'a str {
+        &self.input[start..self.position]
+    }
+
+    fn read_token(&mut self) -> Token<'a> {
         let ch = self.ch;
         let tok = match ch {
             '=' => {
                 if self.peek_char() == '=' {
                     self.read_char();
                     Token::EQ
+                } else if self.peek_char() == '>' {
+                    self.read_char();
+                    Token::FATARROW
                 } else {
                      Token::ASSIGN
                 }
@@ -60,32 +184,64 @@ impl Lexer {
                     Token::BANG
                 }
             },
+            '&' if self.peek_char() == '&' => {
+                self.read_char();
+                Token::AND
+            }
+            '|' if self.peek_char() == '|' => {
+                self.read_char();
+                Token::OR
+            }
+            '|' if self.peek_char() == '>' => {
+                self.read_char();
+                Token::PIPELINE
+            }
+            '&' => Token::AMPERSAND,
+            '|' => Token::PIPE,
+            '^' => Token::CARET,
+            '~' => Token::TILDE,
+            '<' if self.peek_char() == '<' => {
+                self.read_char();
+                Token::SHL
+            }
+            '>' if self.peek_char() == '>' => {
+                self.read_char();
+                Token::SHR
+            }
             ';' => Token::SEMICOLON,
             '(' => Token::LPAREN,
             ')' => Token::RPAREN,
             ',' => Token::COMMA,
             ':' => Token::COLON,
-            '+' => Token::PLUS,
-            '-' => Token::MINUS,
-            '/' => Token::SLASH,
-            '*' => Token::ASTERISK,
-            '<' => Token::LT,
-            '>' => Token::GT,
+            '?' => Token::QUESTION,
+            '.' if self.peek_char() == '.' && self.peek_char_at(1) == '.' => {
+                self.read_char();
+                self.read_char();
+                Token::ELLIPSIS
+            }
+            '.' => Token::DOT,
+            '+' => self.with_assignment(Token::PLUS, Token::PLUSASSIGN),
+            '-' => self.with_assignment(Token::MINUS, Token::MINUSASSIGN),
+            '/' => self.with_assignment(Token::SLASH, Token::SLASHASSIGN),
+            '*' if self.peek_char() == '*' => {
+                self.read_char();
+                Token::POWER
+            }
+            '*' => self.with_assignment(Token::ASTERISK, Token::ASTERISKASSIGN),
+            '%' => Token::PERCENT,
+            '<' => self.with_assignment(Token::LT, Token::LTEQ),
+            '>' => self.with_assignment(Token::GT, Token::GTEQ),
             '{' => Token::LBRACE,
             '}' => Token::RBRACE,
             '['=>Token::LBRACKET,
             ']'=>Token::RBRACKET,
-            '"'=> {
-                let s = self.read_string();
-                Token::STRING(s)
-            },
             ZERO_NULL => Token::EOF,
             other => {
-                if is_letter(other) {
+                if is_identifier_start(other) {
                     let literal = self.read_identifier();
-                    return token::lookup_ident(&literal);
-                } else if other.is_numeric() {
-                    return Token::INT(self.read_number().parse().unwrap());
+                    return token::lookup_ident(literal);
+                } else if other.is_ascii_digit() {
+                    return self.read_number();
                 } else {
                     Token::ILLEGAL
                 }
@@ -94,37 +250,415 @@ impl Lexer {
         self.read_char();
         tok
     }
-    fn read_identifier(&mut self) -> String {
+    // Returns `assignment` for an operator directly followed by `=`, as in
+    // `+=` or `<=`.
+    fn with_assignment(&mut self, operator: Token<'a>, assignment: Token<'a>) -> Token<'a> {
+        if self.peek_char() == '=' {
+            self.read_char();
+            assignment
+        } else {
+            operator
+        }
+    }
+
+    fn read_identifier(&mut self) -> &'a str {
         let curr_pos = self.position;
-        while is_letter(self.ch) {
+        while is_identifier_continue(self.ch) {
             self.read_char();
         }
 
-        let s: String = (self.input[curr_pos..self.position]).iter().collect();
-        s
+        self.slice_from(curr_pos)
     }
 
-    fn read_number(&mut self) -> String {
+    // Reads an integer, or a float when the digits are followed by a fraction
+    // (`3.14`) and/or an exponent (`1e-9`). A dot that is not followed by a
+    // digit is left alone, so `1.` lexes as an integer and a separate token,
+    // while an `e` always starts an exponent, so `1e` is a malformed literal.
+    // Values are decoded here so that malformed or out-of-range literals are
+    // reported at their own position.
+    fn read_number(&mut self) -> Token<'a> {
         let curr_pos = self.position;
-        while self.ch.is_numeric() {
+        if self.ch == '0' {
+            let radix = match self.peek_char() {
+                'x' | 'X' => Some((16, "hexadecimal")),
+                'o' | 'O' => Some((8, "octal")),
+                'b' | 'B' => Some((2, "binary")),
+                _ => None,
+            };
+            if let Some((radix, name)) = radix {
+                self.read_char();
+                self.read_char();
+                return self.read_radix_number(curr_pos, radix, name);
+            }
+        }
+
+        let mut is_float = false;
+        let mut malformed = self.skip_digits(10).err();
+
+        if malformed.is_none() && self.ch == '.' && self.peek_char().is_ascii_digit() {
+            is_float = true;
+            self.read_char();
+            malformed = self.skip_digits(10).err();
+        }
+
+        if malformed.is_none() && (self.ch == 'e' || self.ch == 'E') {
+            is_float = true;
             self.read_char();
+            if self.ch == '+' || self.ch == '-' {
+                self.read_char();
+            }
+            malformed = match self.skip_digits(10) {
+                Ok(true) => None,
+                Ok(false) => Some("missing exponent digits"),
+                Err(problem) => Some(problem),
+            };
         }
 
-        let s: String = (self.input[curr_pos..self.position]).iter().collect();
-        s
+        let literal = self.slice_from(curr_pos);
+        if let Some(problem) = malformed {
+            return Token::ERROR(format!("{} in number literal {}", problem, literal));
+        }
+        if is_float {
+            // only literals with separators need a copy before parsing
+            let digits = match literal.contains('_') {
+                true => Cow::Owned(literal.replace('_', "")),
+                false => Cow::Borrowed(literal),
+            };
+            match digits.parse::<f64>() {
+                Ok(x) if x.is_finite() => Token::FLOAT(x),
+                _ => Token::ERROR(format!("float literal {} is out of range", literal)),
+            }
+        } else {
+            match parse_integer(literal, 10) {
+                Some(i) => Token::INT(i),
+                None => Token::ERROR(format!("integer literal {} is out of range", literal)),
+            }
+        }
     }
 
-    fn read_string(&mut self)->String{
-        let pos = self.position+1;
-        loop{
+    // Reads the digits of a `0x`, `0o` or `0b` literal whose prefix has
+    // already been consumed.
+    fn read_radix_number(&mut self, curr_pos: usize, radix: u32, name: &str) -> Token<'a> {
+        let digits_pos = self.position;
+        let has_digits = match self.skip_digits(radix) {
+            Ok(has_digits) => has_digits,
+            Err(problem) => {
+                let literal = self.slice_from(curr_pos);
+                return Token::ERROR(format!("{} in {} literal {}", problem, name, literal));
+            }
+        };
+        let digits = self.slice_from(digits_pos);
+
+        // swallow the rest of the word so `0b102` is one bad literal, not two tokens
+        let mut invalid = None;
+        while self.ch.is_ascii_alphanumeric() || self.ch == '_' {
+            invalid.get_or_insert(self.ch);
             self.read_char();
-            if self.ch == '"' || self.ch == '\0'{
+        }
+
+        let literal = self.slice_from(curr_pos);
+        if let Some(ch) = invalid {
+            return Token::ERROR(format!("invalid digit '{}' in {} literal {}", ch, name, literal));
+        }
+        if !has_digits {
+            return Token::ERROR(format!("missing digits in {} literal {}", name, literal));
+        }
+
+        match parse_integer(digits, radix) {
+            Some(i) => Token::INT(i),
+            None => Token::ERROR(format!("integer literal {} is out of range", literal)),
+        }
+    }
+
+    // Skips ASCII digits of the given radix along with `_` separators and
+    // reports whether any digit was found. A separator has to sit between two
+    // digits: a repeated one, or one ending the literal, is an error. A
+    // separator starting an identifier is left alone, so `123_test` is still
+    // the integer `123` followed by `_test`.
+    fn skip_digits(&mut self, radix: u32) -> Result<bool, &'static str> {
+        let mut has_digits = false;
+        loop {
+            if self.ch.is_digit(radix) {
+                has_digits = true;
+            } else if self.ch == '_' && has_digits {
+                match self.peek_char() {
+                    ch if ch.is_digit(radix) => {}
+                    '_' => {
+                        // take the rest of the digits so the literal is one error
+                        while self.ch == '_' || self.ch.is_digit(radix) {
+                            self.read_char();
+                        }
+                        return Err("repeated separator");
+                    }
+                    ch if is_identifier_start(ch) => break,
+                    _ => {
+                        self.read_char();
+                        return Err("trailing separator");
+                    }
+                }
+            } else {
                 break;
             }
+            self.read_char();
         }
-        
-        let s = &(self.input[pos..self.position]);
-        s.iter().collect::<String>()
+
+        Ok(has_digits)
+    }
+
+    // Decodes the string literal starting at the opening quote. A malformed
+    // escape does not stop the scan, so lexing resumes after the closing quote,
+    // but the first one is returned as an error token pointing at the escape.
+    // Strings without escapes borrow from the source, and strings containing
+    // `${...}` become a `Token::TEMPLATE` of their parts.
+    fn read_string(&mut self) -> Result<Token<'a>, SpannedToken<'a>> {
+        let start = self.mark();
+        let mut parts = vec![];
+        let mut text_start = self.position + 1;
+        let mut decoded: Option<String> = None;
+        let mut error = None;
+        self.read_char();
+        loop {
+            match self.ch {
+                '"' => break,
+                ZERO_NULL => {
+                    return Err(SpannedToken {
+                        token: Token::ERROR("unterminated string literal".to_string()),
+                        span: self.span_from(start),
+                    })
+                }
+                '\\' => {
+                    let s = decoded
+                        .get_or_insert_with(|| self.input[text_start..self.position].to_string());
+                    match self.read_escape() {
+                        Ok(ch) => s.push(ch),
+                        Err(err) => {
+                            error.get_or_insert(err);
+                        }
+                    }
+                    self.read_char();
+                }
+                '$' if self.peek_char() == '{' => {
+                    let text = self.string_text(text_start, decoded.take());
+                    if !text.is_empty() {
+                        parts.push(StringPart::Text(text));
+                    }
+                    parts.push(StringPart::Expression(self.read_interpolation()?));
+                    text_start = self.position;
+                }
+                ch => {
+                    if let Some(s) = &mut decoded {
+                        s.push(ch);
+                    }
+                    self.read_char();
+                }
+            }
+        }
+        let text = self.string_text(text_start, decoded);
+        self.read_char();
+
+        if let Some(error) = error {
+            return Err(error);
+        }
+        if parts.is_empty() {
+            return Ok(Token::STRING(text));
+        }
+        if !text.is_empty() {
+            parts.push(StringPart::Text(text));
+        }
+        Ok(Token::TEMPLATE(parts))
+    }
+
+    // Text of a string literal from `start` up to `ch`, borrowed unless
+    // escapes had to be decoded.
+    fn string_text(&self, start: usize, decoded: Option<String>) -> Cow<'a, str> {
+        match decoded {
+            Some(s) => Cow::Owned(s),
+            None => Cow::Borrowed(&self.input[start..self.position]),
+        }
+    }
+
+    // Skips over the `${...}` starting at `ch`, returning the span of the
+    // expression inside. The expression is lexed to find the matching brace,
+    // so braces in nested strings and hash literals are handled, and errors
+    // in it are left for the parser to report when it lexes it again.
+    fn read_interpolation(&mut self) -> Result<Span, SpannedToken<'a>> {
+        let start = self.mark();
+        self.read_char();
+        self.read_char();
+        let expression = self.mark();
+        let mut depth = 0;
+        loop {
+            let tok = self.next_token();
+            match tok.token {
+                Token::LBRACE => depth += 1,
+                Token::RBRACE if depth == 0 => {
+                    return Ok(Span {
+                        end: tok.span.start,
+                        ..expression
+                    })
+                }
+                Token::RBRACE => depth -= 1,
+                Token::EOF => {
+                    return Err(SpannedToken {
+                        token: Token::ERROR("unterminated string interpolation".to_string()),
+                        span: self.span_from(start),
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn at_raw_string(&self) -> bool {
+        self.input[self.read_position..]
+            .trim_start_matches('#')
+            .starts_with('"')
+    }
+
+    // Raw strings, `r"..."` or `r#"..."#` with any number of `#`, end at the
+    // first quote followed by as many `#`. Their content is taken verbatim,
+    // without escapes or interpolation, and may span lines.
+    fn read_raw_string(&mut self) -> Result<Token<'a>, String> {
+        self.read_char();
+        let mut hashes = 0;
+        while self.ch == '#' {
+            hashes += 1;
+            self.read_char();
+        }
+        let content_start = self.position + 1;
+        loop {
+            self.read_char();
+            match self.ch {
+                ZERO_NULL => return Err("unterminated raw string literal".to_string()),
+                '"' if self.input[self.read_position..]
+                    .bytes()
+                    .take_while(|&b| b == b'#')
+                    .count()
+                    >= hashes =>
+                {
+                    break
+                }
+                _ => {}
+            }
+        }
+        let content = &self.input[content_start..self.position];
+        for _ in 0..=hashes {
+            self.read_char();
+        }
+
+        Ok(Token::STRING(Cow::Borrowed(content)))
+    }
+
+    // Triple-quoted strings may span lines. A line break right after the
+    // opening quotes and the whitespace before the closing ones are dropped,
+    // and the indentation shared by all non-blank lines is stripped from each
+    // of them. Escapes are decoded as in ordinary strings, at their original
+    // positions, but there is no interpolation.
+    fn read_multiline_string(&mut self) -> Result<Token<'a>, SpannedToken<'a>> {
+        let start = self.mark();
+        let content_start = self.position + 3;
+        let Some(len) = find_closing_quotes(&self.input[content_start..]) else {
+            while self.ch != ZERO_NULL {
+                self.read_char();
+            }
+            return Err(SpannedToken {
+                token: Token::ERROR("unterminated multi-line string literal".to_string()),
+                span: self.span_from(start),
+            });
+        };
+        let content = &self.input[content_start..content_start + len];
+        let (text_start, text_end, indent) = text_block_layout(content);
+        let (text_start, text_end) = (content_start + text_start, content_start + text_end);
+
+        while self.position < text_start {
+            self.read_char();
+        }
+        let mut text = String::new();
+        let mut error = None;
+        let mut line_start = true;
+        while self.position < text_end {
+            if line_start {
+                for _ in 0..indent {
+                    if !matches!(self.ch, ' ' | '\t') || self.position >= text_end {
+                        break;
+                    }
+                    self.read_char();
+                }
+                line_start = false;
+                continue;
+            }
+            match self.ch {
+                '\\' => match self.read_escape() {
+                    Ok(ch) => text.push(ch),
+                    Err(err) => {
+                        error.get_or_insert(err);
+                    }
+                },
+                '\n' => {
+                    text.push('\n');
+                    line_start = true;
+                }
+                ch => text.push(ch),
+            }
+            self.read_char();
+        }
+        while self.position < content_start + len + 3 {
+            self.read_char();
+        }
+
+        match error {
+            Some(error) => Err(error),
+            None => Ok(Token::STRING(Cow::Owned(text))),
+        }
+    }
+
+    // Reads the escape sequence starting at the current backslash, leaving
+    // `ch` on its last character.
+    fn read_escape(&mut self) -> Result<char, SpannedToken<'a>> {
+        let start = self.mark();
+        self.read_char();
+        let escaped = match self.ch {
+            'n' => Ok('\n'),
+            't' => Ok('\t'),
+            'r' => Ok('\r'),
+            '\\' => Ok('\\'),
+            '"' => Ok('"'),
+            '$' => Ok('$'),
+            'u' => self.read_unicode_escape().ok_or("invalid unicode escape"),
+            _ => Err("unknown escape sequence"),
+        };
+
+        escaped.map_err(|message| {
+            let mut span = self.span_from(start);
+            if self.ch != ZERO_NULL {
+                span.end += self.ch.len_utf8();
+            }
+            SpannedToken {
+                token: Token::ERROR(format!("{} {}", message, &self.input[span.start..span.end])),
+                span,
+            }
+        })
+    }
+
+    // Reads the `{XXXX}` part of a `\u{XXXX}` escape. Only characters belonging
+    // to the escape are consumed, so a closing quote is never swallowed.
+    fn read_unicode_escape(&mut self) -> Option<char> {
+        if self.peek_char() != '{' {
+            return None;
+        }
+        self.read_char();
+
+        let digits_pos = self.read_position;
+        while self.peek_char().is_ascii_hexdigit() {
+            self.read_char();
+        }
+        let digits = &self.input[digits_pos..self.read_position];
+        if self.peek_char() != '}' || digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+        self.read_char();
+
+        u32::from_str_radix(digits, 16).ok().and_then(char::from_u32)
     }
 
     fn skip_whitespace(&mut self) {
@@ -132,11 +666,113 @@ impl Lexer {
             self.read_char();
         }
     }
+
+    fn skip_line_comment(&mut self) {
+        while self.ch != '\n' && self.ch != ZERO_NULL {
+            self.read_char();
+        }
+    }
+
+    // Block comments nest, so `/* a /* b */ c */` is a single comment.
+    fn skip_block_comment(&mut self) -> Result<(), String> {
+        let mut depth = 0;
+        loop {
+            match (self.ch, self.peek_char()) {
+                ('/', '*') => {
+                    depth += 1;
+                    self.read_char();
+                }
+                ('*', '/') => {
+                    depth -= 1;
+                    self.read_char();
+                    if depth == 0 {
+                        self.read_char();
+                        return Ok(());
+                    }
+                }
+                (ZERO_NULL, _) => return Err("unterminated block comment".to_string()),
+                _ => {}
+            }
+            self.read_char();
+        }
+    }
+}
+
+impl<'a> Iterator for Lexer<'a> {
+    type Item = SpannedToken<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tok = self.next_token();
+        match tok.token {
+            Token::EOF => None,
+            _ => Some(tok),
+        }
+    }
+}
+
+// Identifiers follow Unicode XID rules (as Rust's do), with `_` also allowed
+// as the first character.
+fn is_identifier_start(ch: char) -> bool {
+    ch == '_' || unicode_ident::is_xid_start(ch)
+}
+
+fn is_identifier_continue(ch: char) -> bool {
+    unicode_ident::is_xid_continue(ch)
+}
+
+// Byte offset of the `"""` closing a multi-line string, skipping over
+// escaped quotes.
+fn find_closing_quotes(content: &str) -> Option<usize> {
+    let mut chars = content.char_indices();
+    while let Some((i, ch)) = chars.next() {
+        match ch {
+            '\\' => {
+                chars.next();
+            }
+            '"' if content[i..].starts_with("\"\"\"") => return Some(i),
+            _ => {}
+        }
+    }
+
+    None
 }
 
+// Start and end offsets of the text of a multi-line string within its
+// content, without a blank first or last line, and the width of the
+// indentation shared by its non-blank lines.
+fn text_block_layout(content: &str) -> (usize, usize, usize) {
+    let mut start = 0;
+    let mut end = content.len();
+    if let Some(i) = content.find('\n') {
+        if content[..i].trim().is_empty() {
+            start = i + 1;
+        }
+    }
+    if let Some(i) = content.rfind('\n') {
+        if i + 1 >= start && content[i + 1..].trim().is_empty() {
+            end = i;
+        }
+    }
+    let end = end.max(start);
+    let indent = content[start..end]
+        .split('\n')
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.chars().take_while(|ch| matches!(ch, ' ' | '\t')).count())
+        .min()
+        .unwrap_or(0);
+
+    (start, end, indent)
+}
 
-fn is_letter(ch: char) -> bool {
-    'a' <= ch && ch <= 'z' || 'A' <= ch && ch <= 'Z' || ch == '_'
+// Parses digits of the given radix, skipping `_` separators, without
+// allocating. Returns `None` when the value does not fit in an i64.
+fn parse_integer(digits: &str, radix: u32) -> Option<i64> {
+    digits
+        .chars()
+        .filter_map(|ch| ch.to_digit(radix))
+        .try_fold(0i64, |acc, digit| {
+            acc.checked_mul(radix as i64)?.checked_add(digit as i64)
+        })
 }
 
 #[cfg(test)]
@@ -154,7 +790,7 @@ mod tests {
 
         let result = add(five, ten);
 
-        !-/*5;
+        !-/ *5;
         5 < 10 > 5;
 
         if (5 < 10) {
@@ -171,58 +807,58 @@ mod tests {
         ";
         let tests = vec![
         Token::LET,
-        Token::IDENT("five".to_string()),
+        Token::IDENT("five"),
         Token::ASSIGN,
-        Token::INT("5".to_string()),
+        Token::INT(5),
         Token::SEMICOLON,
         Token::LET,
-        Token::IDENT("ten".to_string()),
+        Token::IDENT("ten"),
         Token::ASSIGN,
-        Token::INT("10".to_string()),
+        Token::INT(10),
         Token::SEMICOLON,
         Token::LET,
-        Token::IDENT("add".to_string()),
+        Token::IDENT("add"),
         Token::ASSIGN,
         Token::FUNCTION,
         Token::LPAREN,
-        Token::IDENT("x".to_string()),
+        Token::IDENT("x"),
         Token::COMMA,
-        Token::IDENT("y".to_string()),
+        Token::IDENT("y"),
         Token::RPAREN,
         Token::LBRACE,
-        Token::IDENT("x".to_string()),
+        Token::IDENT("x"),
         Token::PLUS,
-        Token::IDENT("y".to_string()),
+        Token::IDENT("y"),
         Token::SEMICOLON,
         Token::RBRACE,
         Token::SEMICOLON,
         Token::LET,
-        Token::IDENT("result".to_string()),
+        Token::IDENT("result"),
         Token::ASSIGN,
-        Token::IDENT("add".to_string()),
+        Token::IDENT("add"),
         Token::LPAREN,
-        Token::IDENT("five".to_string()),
+        Token::IDENT("five"),
         Token::COMMA,
-        Token::IDENT("ten".to_string()),
+        Token::IDENT("ten"),
         Token::RPAREN,
         Token::SEMICOLON,
         Token::BANG,
         Token::MINUS,
         Token::SLASH,
         Token::ASTERISK,
-        Token::INT("5".to_string()),
+        Token::INT(5),
         Token::SEMICOLON,
-        Token::INT("5".to_string()),
+        Token::INT(5),
         Token::LT,
-        Token::INT("10".to_string()),
+        Token::INT(10),
         Token::GT,
-        Token::INT("5".to_string()),
+        Token::INT(5),
         Token::SEMICOLON,
         Token::IF,
         Token::LPAREN,
-        Token::INT("5".to_string()),
+        Token::INT(5),
         Token::LT,
-        Token::INT("10".to_string()),
+        Token::INT(10),
         Token::RPAREN,
         Token::LBRACE,
         Token::RETURN,
@@ -235,26 +871,642 @@ mod tests {
         Token::BOOLEAN(false),
         Token::SEMICOLON,
         Token::RBRACE,
-        Token::INT("10".to_string()),
+        Token::INT(10),
         Token::EQ,
-        Token::INT("10".to_string()),
+        Token::INT(10),
         Token::SEMICOLON,
-        Token::INT("10".to_string()),
+        Token::INT(10),
         Token::NOTEQ,
-        Token::INT("9".to_string()),
+        Token::INT(9),
         Token::SEMICOLON,
-        Token::STRING("foobar".to_string()),
-        Token::STRING("foo bar".to_string()),
+        Token::STRING("foobar".into()),
+        Token::STRING("foo bar".into()),
         Token::EOF,
         ];
 
         let mut l = Lexer::new(input);
 
-        for (_, elem) in tests.iter().enumerate() {
+        for elem in tests.iter() {
             let tok = l.next_token();
             assert_eq!(
-                *elem, tok
+                *elem, tok.token
             )
         }
     }
+
+    #[test]
+    fn test_loop_keywords() {
+        let tokens: Vec<Token> = Lexer::new("while for in break continue whiles index")
+            .map(|tok| tok.token)
+            .collect();
+        assert_eq!(
+            vec![
+                Token::WHILE,
+                Token::FOR,
+                Token::IN,
+                Token::BREAK,
+                Token::CONTINUE,
+                Token::IDENT("whiles"),
+                Token::IDENT("index"),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_logical_operators() {
+        let tokens: Vec<Token> = Lexer::new("a && b || !c").map(|tok| tok.token).collect();
+        assert_eq!(
+            vec![
+                Token::IDENT("a"),
+                Token::AND,
+                Token::IDENT("b"),
+                Token::OR,
+                Token::BANG,
+                Token::IDENT("c"),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_conditional_operator() {
+        let tokens: Vec<Token> = Lexer::new("a?b:c").map(|tok| tok.token).collect();
+        assert_eq!(
+            vec![
+                Token::IDENT("a"),
+                Token::QUESTION,
+                Token::IDENT("b"),
+                Token::COLON,
+                Token::IDENT("c"),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_match_tokens() {
+        let tokens: Vec<Token> = Lexer::new("match (x) { [a, ...rest] => a, _ => 0 } ..")
+            .map(|tok| tok.token)
+            .collect();
+        assert_eq!(
+            vec![
+                Token::MATCH,
+                Token::LPAREN,
+                Token::IDENT("x"),
+                Token::RPAREN,
+                Token::LBRACE,
+                Token::LBRACKET,
+                Token::IDENT("a"),
+                Token::COMMA,
+                Token::ELLIPSIS,
+                Token::IDENT("rest"),
+                Token::RBRACKET,
+                Token::FATARROW,
+                Token::IDENT("a"),
+                Token::COMMA,
+                Token::IDENT("_"),
+                Token::FATARROW,
+                Token::INT(0),
+                Token::RBRACE,
+                Token::DOT,
+                Token::DOT,
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_member_access() {
+        let tokens: Vec<Token> = Lexer::new("arr.rest().len 1.abs 1.5.floor")
+            .map(|tok| tok.token)
+            .collect();
+        assert_eq!(
+            vec![
+                Token::IDENT("arr"),
+                Token::DOT,
+                Token::IDENT("rest"),
+                Token::LPAREN,
+                Token::RPAREN,
+                Token::DOT,
+                Token::IDENT("len"),
+                Token::INT(1),
+                Token::DOT,
+                Token::IDENT("abs"),
+                Token::FLOAT(1.5),
+                Token::DOT,
+                Token::IDENT("floor"),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_pipeline_operator() {
+        let tokens: Vec<Token> = Lexer::new("xs |> len() | x || y|>f").map(|tok| tok.token).collect();
+        assert_eq!(
+            vec![
+                Token::IDENT("xs"),
+                Token::PIPELINE,
+                Token::IDENT("len"),
+                Token::LPAREN,
+                Token::RPAREN,
+                Token::PIPE,
+                Token::IDENT("x"),
+                Token::OR,
+                Token::IDENT("y"),
+                Token::PIPELINE,
+                Token::IDENT("f"),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_comparison_and_arithmetic_operators() {
+        let tokens: Vec<Token> = Lexer::new("a <= b >= c % d ** e * f < g > h ***= i")
+            .map(|tok| tok.token)
+            .collect();
+        assert_eq!(
+            vec![
+                Token::IDENT("a"),
+                Token::LTEQ,
+                Token::IDENT("b"),
+                Token::GTEQ,
+                Token::IDENT("c"),
+                Token::PERCENT,
+                Token::IDENT("d"),
+                Token::POWER,
+                Token::IDENT("e"),
+                Token::ASTERISK,
+                Token::IDENT("f"),
+                Token::LT,
+                Token::IDENT("g"),
+                Token::GT,
+                Token::IDENT("h"),
+                Token::POWER,
+                Token::ASTERISKASSIGN,
+                Token::IDENT("i"),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let tokens: Vec<Token> = Lexer::new("a & b | c ^ ~d << 2 >> 1 && e || f <= g")
+            .map(|tok| tok.token)
+            .collect();
+        assert_eq!(
+            vec![
+                Token::IDENT("a"),
+                Token::AMPERSAND,
+                Token::IDENT("b"),
+                Token::PIPE,
+                Token::IDENT("c"),
+                Token::CARET,
+                Token::TILDE,
+                Token::IDENT("d"),
+                Token::SHL,
+                Token::INT(2),
+                Token::SHR,
+                Token::INT(1),
+                Token::AND,
+                Token::IDENT("e"),
+                Token::OR,
+                Token::IDENT("f"),
+                Token::LTEQ,
+                Token::IDENT("g"),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_assignment_operators() {
+        let tokens: Vec<Token> = Lexer::new("x = 1; x += 2 -= 3 *= 4 /= 5 == 6 + -7")
+            .map(|tok| tok.token)
+            .collect();
+        assert_eq!(
+            vec![
+                Token::IDENT("x"),
+                Token::ASSIGN,
+                Token::INT(1),
+                Token::SEMICOLON,
+                Token::IDENT("x"),
+                Token::PLUSASSIGN,
+                Token::INT(2),
+                Token::MINUSASSIGN,
+                Token::INT(3),
+                Token::ASTERISKASSIGN,
+                Token::INT(4),
+                Token::SLASHASSIGN,
+                Token::INT(5),
+                Token::EQ,
+                Token::INT(6),
+                Token::PLUS,
+                Token::MINUS,
+                Token::INT(7),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "// leading comment
+        let x = 5; // trailing comment
+        /* block
+           /* nested */ still a comment */
+        x /* inline */ / 2;
+        //";
+        let tests = vec![
+            Token::LET,
+            Token::IDENT("x"),
+            Token::ASSIGN,
+            Token::INT(5),
+            Token::SEMICOLON,
+            Token::IDENT("x"),
+            Token::SLASH,
+            Token::INT(2),
+            Token::SEMICOLON,
+            Token::EOF,
+        ];
+
+        let mut l = Lexer::new(input);
+
+        for elem in tests.iter() {
+            assert_eq!(*elem, l.next_token().token)
+        }
+    }
+
+    #[test]
+    fn test_trivia_tokens() {
+        let input = "x  // note\n/* a /* b */ */+";
+        let tokens: Vec<(Token, &str)> = Lexer::new(input)
+            .with_trivia()
+            .map(|t| (t.token, &input[t.span.start..t.span.end]))
+            .collect();
+
+        assert_eq!(
+            vec![
+                (Token::IDENT("x"), "x"),
+                (Token::WHITESPACE, "  "),
+                (Token::COMMENT, "// note"),
+                (Token::WHITESPACE, "\n"),
+                (Token::COMMENT, "/* a /* b */ */"),
+                (Token::PLUS, "+"),
+            ],
+            tokens
+        );
+    }
+
+    #[test]
+    fn test_unterminated_block_comment() {
+        let mut l = Lexer::new("1 /* open /* nested */");
+
+        assert_eq!(Token::INT(1), l.next_token().token);
+        let tok = l.next_token();
+        assert_eq!(Token::ERROR("unterminated block comment".to_string()), tok.token);
+        assert_eq!((1, 3), (tok.span.line, tok.span.column));
+        assert_eq!(Token::EOF, l.next_token().token);
+    }
+
+    #[test]
+    fn test_float_literals() {
+        let input = "2.75 0.5 1e-9 2.5E+3 7e2 10 1.len 4e x";
+        let tests = vec![
+            Token::FLOAT(2.75),
+            Token::FLOAT(0.5),
+            Token::FLOAT(1e-9),
+            Token::FLOAT(2.5E+3),
+            Token::FLOAT(7e2),
+            Token::INT(10),
+            Token::INT(1),
+            Token::DOT,
+            Token::IDENT("len"),
+            Token::ERROR("missing exponent digits in number literal 4e".to_string()),
+            Token::IDENT("x"),
+            Token::EOF,
+        ];
+
+        let mut l = Lexer::new(input);
+
+        for elem in tests.iter() {
+            assert_eq!(*elem, l.next_token().token)
+        }
+    }
+
+    #[test]
+    fn test_integer_literals() {
+        let input = "0xFF 0Xff 0o17 0b1010 1_000_000 0x_1 0b1_0 007 1_000.5 123_test 9223372036854775807";
+        let tests = vec![
+            Token::INT(255),
+            Token::INT(255),
+            Token::INT(15),
+            Token::INT(10),
+            Token::INT(1_000_000),
+            Token::ERROR("invalid digit '_' in hexadecimal literal 0x_1".to_string()),
+            Token::INT(2),
+            Token::INT(7),
+            Token::FLOAT(1000.5),
+            Token::INT(123),
+            Token::IDENT("_test"),
+            Token::INT(i64::MAX),
+            Token::EOF,
+        ];
+
+        let mut l = Lexer::new(input);
+
+        for elem in tests.iter() {
+            assert_eq!(*elem, l.next_token().token)
+        }
+    }
+
+    #[test]
+    fn test_malformed_number_literals() {
+        let tests = vec![
+            ("99999999999999999999", "integer literal 99999999999999999999 is out of range"),
+            ("0x8000000000000000", "integer literal 0x8000000000000000 is out of range"),
+            ("1e400", "float literal 1e400 is out of range"),
+            ("0b102", "invalid digit '2' in binary literal 0b102"),
+            ("0o8", "invalid digit '8' in octal literal 0o8"),
+            ("0xfg", "invalid digit 'g' in hexadecimal literal 0xfg"),
+            ("0x", "missing digits in hexadecimal literal 0x"),
+            ("1__0", "repeated separator in number literal 1__0"),
+            ("1_", "trailing separator in number literal 1_"),
+            ("2.5_", "trailing separator in number literal 2.5_"),
+            ("1e", "missing exponent digits in number literal 1e"),
+            ("1.5e+", "missing exponent digits in number literal 1.5e+"),
+            ("1e5__0", "repeated separator in number literal 1e5__0"),
+            ("0xf__f", "repeated separator in hexadecimal literal 0xf__f"),
+            ("0b1_", "trailing separator in binary literal 0b1_"),
+        ];
+
+        for (input, message) in tests {
+            let mut l = Lexer::new(input);
+            let tok = l.next_token();
+            assert_eq!(Token::ERROR(message.to_string()), tok.token);
+            assert_eq!((0, input.len()), (tok.span.start, tok.span.end));
+            assert_eq!(Token::EOF, l.next_token().token);
+        }
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let input = "x1 row2 user_id_v3 _tmp größe 名前 café=1 x·y";
+        let tests = vec![
+            Token::IDENT("x1"),
+            Token::IDENT("row2"),
+            Token::IDENT("user_id_v3"),
+            Token::IDENT("_tmp"),
+            Token::IDENT("größe"),
+            Token::IDENT("名前"),
+            Token::IDENT("café"),
+            Token::ASSIGN,
+            Token::INT(1),
+            Token::IDENT("x·y"),
+            Token::EOF,
+        ];
+
+        let mut l = Lexer::new(input);
+
+        for elem in tests.iter() {
+            assert_eq!(*elem, l.next_token().token)
+        }
+    }
+
+    #[test]
+    fn test_non_ascii_digits() {
+        let mut l = Lexer::new("\u{663}");
+
+        assert_eq!(Token::ILLEGAL, l.next_token().token);
+        assert_eq!(Token::EOF, l.next_token().token);
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let tests = vec![
+            (r#""a\nb\tc""#, "a\nb\tc"),
+            (r#""say \"hi\"""#, "say \"hi\""),
+            (r#""back\\slash""#, "back\\slash"),
+            (r#""\u{48}\u{e9}\u{1F600}""#, "Hé😀"),
+            (r#""cr\r""#, "cr\r"),
+        ];
+
+        for (input, expected) in tests {
+            let mut l = Lexer::new(input);
+            assert_eq!(Token::STRING(expected.into()), l.next_token().token);
+            assert_eq!(Token::EOF, l.next_token().token);
+        }
+    }
+
+    #[test]
+    fn test_raw_strings() {
+        let tests = vec![
+            (r#"r"C:\path\n""#, r"C:\path\n"),
+            (r##"r#"say "hi" ${x}"#"##, r#"say "hi" ${x}"#),
+            (r###"r##"a "# b"##"###, r##"a "# b"##),
+            ("r\"two\nlines\"", "two\nlines"),
+            (r#"r"""#, ""),
+        ];
+
+        for (input, expected) in tests {
+            let mut l = Lexer::new(input);
+            let tok = l.next_token();
+            assert_eq!(Token::STRING(expected.into()), tok.token, "{}", input);
+            assert_eq!(input.len(), tok.span.end);
+            assert_eq!(Token::EOF, l.next_token().token);
+        }
+
+        let tokens: Vec<Token> = Lexer::new("r + r#").map(|tok| tok.token).collect();
+        assert_eq!(vec![Token::IDENT("r"), Token::PLUS, Token::IDENT("r"), Token::ILLEGAL], tokens);
+
+        let tok = Lexer::new("let s = r#\"never \" closed").nth(3).unwrap();
+        assert_eq!(Token::ERROR("unterminated raw string literal".to_string()), tok.token);
+        assert_eq!((1, 9), (tok.span.line, tok.span.column));
+    }
+
+    #[test]
+    fn test_multiline_strings() {
+        let tests = vec![
+            ("\"\"\"\n    SELECT *\n      FROM t\n    \"\"\"", "SELECT *\n  FROM t"),
+            ("\"\"\"one line\"\"\"", "one line"),
+            ("\"\"\"\n\ta\n\n\t\\tb \\\"\"\" \"\"\"", "a\n\n\tb \"\"\" "),
+            ("\"\"\"\n  first\n  last\n\"\"\"", "first\nlast"),
+            ("\"\"\"\n    \"\"\"", ""),
+        ];
+
+        for (input, expected) in tests {
+            let mut l = Lexer::new(input);
+            let tok = l.next_token();
+            assert_eq!(Token::STRING(expected.into()), tok.token, "{:?}", input);
+            assert_eq!(input.len(), tok.span.end);
+            assert_eq!(Token::EOF, l.next_token().token);
+        }
+    }
+
+    #[test]
+    fn test_multiline_string_positions() {
+        let input = "let s = \"\"\"\n    a\n    b \\q\n    \"\"\";\ns";
+        let tokens: Vec<(Token, usize, usize)> = Lexer::new(input)
+            .map(|tok| (tok.token, tok.span.line, tok.span.column))
+            .collect();
+        assert_eq!(
+            vec![
+                (Token::LET, 1, 1),
+                (Token::IDENT("s"), 1, 5),
+                (Token::ASSIGN, 1, 7),
+                (Token::ERROR("unknown escape sequence \\q".to_string()), 3, 7),
+                (Token::SEMICOLON, 4, 8),
+                (Token::IDENT("s"), 5, 1),
+            ],
+            tokens
+        );
+
+        let tok = Lexer::new("x = \"\"\"\nopen \\\"\"\"").nth(2).unwrap();
+        assert_eq!(Token::ERROR("unterminated multi-line string literal".to_string()), tok.token);
+        assert_eq!((1, 5), (tok.span.line, tok.span.column));
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let input = r#""a ${x + "}"} b${ {1: 2}[1] }""#;
+        let mut l = Lexer::new(input);
+        let tok = l.next_token();
+        let Token::TEMPLATE(parts) = tok.token else {
+            panic!("expected an interpolated string, got {:?}", tok.token);
+        };
+
+        let parts: Vec<String> = parts
+            .iter()
+            .map(|part| match part {
+                StringPart::Text(text) => format!("text {}", text),
+                StringPart::Expression(span) => format!("expr {}", &input[span.start..span.end]),
+            })
+            .collect();
+        assert_eq!(
+            vec!["text a ", "expr x + \"}\"", "text  b", "expr  {1: 2}[1] "],
+            parts
+        );
+        assert_eq!(input.len(), tok.span.end);
+        assert_eq!(Token::EOF, l.next_token().token);
+
+        let mut l = Lexer::new(r#""\${x} $y""#);
+        assert_eq!(Token::STRING("${x} $y".into()), l.next_token().token);
+    }
+
+    #[test]
+    fn test_embedded_lexer() {
+        let input = "\"line\n${ a +\n b }\"";
+        let Token::TEMPLATE(parts) = Lexer::new(input).next_token().token else {
+            panic!("expected an interpolated string");
+        };
+        let StringPart::Expression(span) = parts[1] else {
+            panic!("expected an expression part");
+        };
+
+        let spans: Vec<(Token, usize, usize)> = Lexer::embedded(input, span)
+            .map(|tok| (tok.token, tok.span.line, tok.span.column))
+            .collect();
+        assert_eq!(
+            vec![
+                (Token::IDENT("a"), 2, 4),
+                (Token::PLUS, 2, 6),
+                (Token::IDENT("b"), 3, 2),
+            ],
+            spans
+        );
+    }
+
+    #[test]
+    fn test_string_errors() {
+        let tests = vec![
+            ("\"never closed", "unterminated string literal", 1, 1),
+            ("let s = \"ab\\q\";", "unknown escape sequence \\q", 1, 12),
+            ("\"x\\u{110000}\"", "invalid unicode escape \\u{110000}", 1, 3),
+            ("\"x\\u{zz}\"", "invalid unicode escape \\u{", 1, 3),
+            ("\"line\none \\x\"", "unknown escape sequence \\x", 2, 5),
+            ("\"trailing\\", "unterminated string literal", 1, 1),
+            ("\"a ${b", "unterminated string interpolation", 1, 4),
+        ];
+
+        for (input, message, line, column) in tests {
+            let mut l = Lexer::new(input);
+            let tok = loop {
+                let tok = l.next_token();
+                if let Token::ERROR(_) = tok.token {
+                    break tok;
+                }
+                assert_ne!(Token::EOF, tok.token);
+            };
+            assert_eq!(Token::ERROR(message.to_string()), tok.token);
+            assert_eq!((line, column), (tok.span.line, tok.span.column));
+        }
+    }
+
+    #[test]
+    fn test_lexing_resumes_after_bad_escape() {
+        let mut l = Lexer::new("\"\\q\" + 1");
+
+        assert!(matches!(l.next_token().token, Token::ERROR(_)));
+        assert_eq!(Token::PLUS, l.next_token().token);
+        assert_eq!(Token::INT(1), l.next_token().token);
+    }
+
+    #[test]
+    fn test_tokens_borrow_from_source() {
+        let input = String::from("let name = \"plain\"; \"esc\\n\"");
+        let tokens: Vec<SpannedToken> = Lexer::new(&input).collect();
+
+        match (&tokens[1].token, &tokens[3].token, &tokens[5].token) {
+            (Token::IDENT(name), Token::STRING(Cow::Borrowed(plain)), Token::STRING(Cow::Owned(esc))) => {
+                assert_eq!(&input[tokens[1].span.start..tokens[1].span.end], *name);
+                assert!(std::ptr::eq(input[4..8].as_ptr(), name.as_ptr()));
+                assert_eq!("plain", *plain);
+                assert_eq!("esc\n", esc);
+            }
+            other => panic!("unexpected tokens {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_iterator_stops_at_eof() {
+        let tokens: Vec<Token> = Lexer::new("1 + 2 // done").map(|t| t.token).collect();
+
+        assert_eq!(vec![Token::INT(1), Token::PLUS, Token::INT(2)], tokens);
+        assert_eq!(0, Lexer::new("  /* only trivia */ ").count());
+    }
+
+    #[test]
+    fn test_token_spans() {
+        let input = "let x = 5;\n  \"héllo\" + y";
+        let tests = vec![
+            (Token::LET, 0, 3, 1, 1),
+            (Token::IDENT("x"), 4, 5, 1, 5),
+            (Token::ASSIGN, 6, 7, 1, 7),
+            (Token::INT(5), 8, 9, 1, 9),
+            (Token::SEMICOLON, 9, 10, 1, 10),
+            (Token::STRING("héllo".into()), 13, 21, 2, 3),
+            (Token::PLUS, 22, 23, 2, 11),
+            (Token::IDENT("y"), 24, 25, 2, 13),
+            (Token::EOF, 25, 25, 2, 14),
+        ];
+
+        let mut l = Lexer::new(input);
+
+        for (token, start, end, line, column) in tests {
+            let tok = l.next_token();
+            assert_eq!(token, tok.token);
+            assert_eq!(
+                Span {
+                    start,
+                    end,
+                    line,
+                    column
+                },
+                tok.span
+            );
+        }
+    }
 }
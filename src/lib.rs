@@ -0,0 +1,8 @@
+pub mod ast;
+pub mod cst;
+pub mod evaluator;
+pub mod lexer;
+pub mod object;
+pub mod parser;
+pub mod repl;
+pub mod token;
@@ -1,12 +1,6 @@
 use std::io::{self};
 
-mod lexer;
-mod repl;
-mod token;
-mod ast;
-mod parser;
-mod object;
-mod evaluator;
+use interpreter_in_rust::repl;
 
 fn main() {
     println!("Feel free to type in commands");
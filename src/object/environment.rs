@@ -18,7 +18,7 @@ impl Environment{
     pub fn new_enclosed_environment(outer: Env)->Self{
         let mut env =  Self::new();
         env.outer = Some(outer.clone());
-        return env;
+        env
     }
 
     pub fn get(&self,name: &str)->Option<Rc<Object>>{
@@ -36,4 +36,19 @@ impl Environment{
     pub fn set(&mut self, name:&str, object:Rc<Object>){
         self.store.insert(name.to_string(), object);
     }
+
+    /// Rebinds `name` in the innermost scope that defines it. Returns false
+    /// when no scope does.
+    pub fn assign(&mut self, name: &str, object: Rc<Object>) -> bool {
+        match self.store.get_mut(name) {
+            Some(slot) => {
+                *slot = object;
+                true
+            }
+            None => match &self.outer {
+                Some(outer) => outer.borrow_mut().assign(name, object),
+                None => false,
+            },
+        }
+    }
 }
\ No newline at end of file
@@ -4,19 +4,22 @@ use std::{fmt, rc::Rc};
 use std::hash::{Hash, Hasher};
 
 use self::environment::Env;
-use crate::ast::BlockStatement;
+use crate::ast::{BlockStatement, Parameter};
 use crate::evaluator::builtins::Builtin;
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Object {
     Integer(i64),
+    Float(f64),
     Boolean(bool),
     String(String),
     ReturnValue(Rc<Object>),
-    Funtion(Option<Vec<String>>, BlockStatement, Env),
+    Funtion(Option<Vec<Parameter>>, BlockStatement, Env),
     Builtin(Builtin),
     Array(Vec<Rc<Object>>),
     Hash(HashMap<Rc<Object>, Rc<Object>>),
+    // lazy sequence of integers from start (inclusive) to end (exclusive) by step
+    Range(i64, i64, i64),
     Null,
 }
 
@@ -26,14 +29,17 @@ impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Object::Integer(i) => write!(f, "{}", i),
+            // Debug formatting always keeps a fraction or exponent (`2.0`,
+            // `1e-9`), so floats never print like integers
+            Object::Float(x) => write!(f, "{:?}", x),
             Object::Boolean(b) => write!(f, "{}", b),
             Object::String(s) => write!(f, "{}", s),
             Object::ReturnValue(v) => write!(f, "{}", v),
             Object::Funtion(params, body, _) => match params {
-                Some(params) => writeln!(f, "fn({}) {{\n{}\n}}", params.join(", "), body),
+                Some(params) => writeln!(f, "fn({}) {{\n{}\n}}", get_parameter_string(params), body),
                 None => writeln!(f, "fn() {{\n{}\n}}", body),
             },
-            Object::Array(elems) => writeln!(f, "[{}]", get_array_element_string(elems)),
+            Object::Array(elems) => write!(f, "[{}]", get_array_element_string(elems)),
             Object::Hash(map) => {
                 let map = map
                     .iter()
@@ -44,6 +50,7 @@ impl fmt::Display for Object {
                 write!(f, "{{{}}}", map)
             }
             Object::Builtin(b) => write!(f, "Builtin Function: {}", b),
+            Object::Range(start, end, step) => write!(f, "range({}, {}, {})", start, end, step),
             Object::Null => write!(f, "NULL"),
         }
     }
@@ -53,11 +60,12 @@ impl Object {
     pub fn get_type(&self) -> String {
         match self {
             Object::Integer(i) => format!("Integer({})", i),
+            Object::Float(x) => format!("Float({:?})", x),
             Object::Boolean(b) => format!("Boolean({})", b),
             Object::String(s) => format!("String(\"{}\")", s),
             Object::ReturnValue(v) => format!("{}", v),
             Object::Funtion(params, body, _) => match params {
-                Some(params) => format!("Function({}) {{\n{}\n}}", params.join(", "), body),
+                Some(params) => format!("Function({}) {{\n{}\n}}", get_parameter_string(params), body),
                 None => format!("Function() {{\n{}\n}}", body),
             },
             Object::Array(elems) => format!("Array[{}]", get_array_element_string(elems)),
@@ -70,15 +78,13 @@ impl Object {
                 format!("Map{{{}}}", map)
             }
             Object::Builtin(b) => format!("Builtin Function {}", b),
-            Object::Null => format!("NULL"),
+            Object::Range(start, end, step) => format!("Range({}, {}, {})", start, end, step),
+            Object::Null => "NULL".to_string(),
         }
     }
 
     pub fn is_hashtable(&self)->bool{
-        match self{
-            Self::Integer(_) | Self::Boolean(_) | Self::String(_) => true,
-            _ => false,
-        }
+        matches!(self, Self::Integer(_) | Self::Boolean(_) | Self::String(_))
     }
 }
 
@@ -88,11 +94,19 @@ impl Hash for Object{
             Object::Integer(i)=>i.hash(state),
             Object::String(s)=>s.hash(state),
             Object::Boolean(b)=>b.hash(state),
-            _=>!unreachable!()
+            _=>unreachable!()
         }
     }
 }
 
+fn get_parameter_string(params: &[Parameter]) -> String {
+    params
+        .iter()
+        .map(|param| param.to_string())
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
 fn get_array_element_string(elems: &Vec<Rc<Object>>) -> String {
     let mut arr = vec![];
     for e in elems {
@@ -1,40 +1,47 @@
 use std::fmt::{self};
 
-use crate::token;
+use crate::token::{self, Span};
 
 
 
 #[derive(Debug,Clone)]
-pub struct ParseError(String);
+pub struct ParseError {
+    message: String,
+    span: Span,
+}
 
 
 
 impl fmt::Display for ParseError{
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        return write!(f, "{}", self.0);
+        write!(f, "{}: {}", self.span, self.message)
     }
 }
 
 impl ParseError{
-    pub fn new(err: String)->Self{
-        Self(err)
+    pub fn new(message: String, span: Span)->Self{
+        Self { message, span }
     }
 
-    pub fn parse_identifier_error(token: &token::Token)->Self{
-        Self::new(format!("Expected an Identifier but got {}", token) )
+    pub fn parse_identifier_error(token: &token::SpannedToken)->Self{
+        Self::new(format!("Expected an Identifier but got {}", token.token), token.span)
+    }
+    pub fn parse_next_token_error(expect: &token::Token, actual:&token::SpannedToken)->Self{
+        Self::new(format!("Expected token to be {} but got {}", expect, actual.token), actual.span)
     }
-    pub fn parse_next_token_error(expect: &token::Token, actual:&token::Token)->Self{
-        Self::new(format!("Expected token to be {} but got {}", expect, actual) )
+    pub fn lexer_error(message: &str, span: Span)->Self{
+        Self::new(message.to_string(), span)
     }
-    pub fn parse_integer_error(num:&str)->Self{
-        Self::new(format!("Cannot parse {} into integer", num) )
+
+    pub fn illegal_token_error(span: Span)->Self{
+        Self::new("Cannot parse illegal token".to_string(), span)
     }
 
-    pub fn illegal_token_error()->Self{
-        Self::new(format!("Cannot parse illegal token") )
+    pub fn parse_pattern_error(token: &token::SpannedToken)->Self{
+        Self::new(format!("Expected a pattern but got {}", token.token), token.span)
     }
 
-    pub fn unrecognizable_token_error()->Self{
-        Self::new(format!("Unrecognizable Token") )
+    pub fn unrecognizable_token_error(token: &token::SpannedToken)->Self{
+        Self::new(format!("Unrecognizable Token {}", token.token), token.span)
     }
-}
\ No newline at end of file
+}
@@ -1,338 +1,873 @@
-use std::collections::BTreeMap;
-
-use crate::{ast::*, lexer::Lexer, token::*};
+use crate::{
+    ast::*,
+    cst::{assigned_name, lower, lower_expression, SyntaxKind, SyntaxNode, SyntaxTreeBuilder},
+    lexer::Lexer,
+    token::*,
+};
 
 mod error;
 mod precedence;
-use self::error::ParseError;
+pub use self::error::ParseError;
 use precedence::*;
-pub struct Parser {
-    lexer: Lexer,
-    curr_token: Token,
-    peek_token: Token,
+
+/// Parser building the lossless syntax tree of a program. The AST is not
+/// built here but lowered from the finished tree, see `cst::lower`.
+pub struct Parser<'a> {
+    lexer: Lexer<'a>,
+    curr_token: SpannedToken<'a>,
+    peek_token: SpannedToken<'a>,
     errors: Vec<ParseError>,
+    syntax: SyntaxTreeBuilder<'a>,
+    // brackets opened and not yet closed before the current token
+    nesting: usize,
+    // loops enclosing the current token within the current function
+    loop_depth: usize,
+}
+
+// What checking a parameter needs to know about the ones before it.
+#[derive(Default)]
+struct PreviousParameters<'a> {
+    names: Vec<&'a str>,
+    has_default: bool,
+    has_rest: bool,
 }
 
 pub fn start_parsing(input: &str) -> Result<Node, Vec<ParseError>> {
+    parse_syntax_tree(input).0
+}
+
+/// Parses `input` into its lossless syntax tree, together with the AST
+/// lowered from it. The tree is built even when the program has errors.
+pub fn parse_syntax_tree(input: &str) -> (Result<Node, Vec<ParseError>>, SyntaxNode<'_>) {
     let lexer = Lexer::new(input);
     let mut parser = Parser::new(lexer);
-    let program = parser.parse_program()?;
+    let parsed = parser.parse_program();
+    let tree = parser.into_syntax_tree();
+    let program = parsed
+        .and_then(|()| lower(input, &tree).map_err(|err| vec![err]))
+        .map(Node::Program);
+
+    (program, tree)
+}
+
+/// Syntax tree of the `${...}` interpolation at `span` in `source`, whose
+/// expression is the only node of the tree. `source` must already have
+/// parsed without errors.
+pub(crate) fn parse_embedded_syntax_tree(source: &str, span: Span) -> SyntaxNode<'_> {
+    let lexer = Lexer::embedded(source, span);
+    let mut parser = Parser::new(lexer);
+    // a `break` in the interpolation was accepted by the enclosing loop
+    parser.loop_depth = 1;
+    let _ = parser.parse_embedded_expression(span);
 
-    Ok(Node::Program(program))
+    parser.into_syntax_tree()
 }
 
-impl Parser {
-    pub fn new(mut lexer: Lexer) -> Self {
-        let curr_token = lexer.next_token();
-        let peek_token = lexer.next_token();
+impl<'a> Parser<'a> {
+    pub fn new(lexer: Lexer<'a>) -> Self {
+        let mut lexer = lexer.with_trivia();
+        let mut syntax = SyntaxTreeBuilder::new(lexer.source());
+        let curr_token = Self::fetch_token(&mut lexer, &mut syntax);
+        syntax.token(&curr_token);
+        let peek_token = Self::fetch_token(&mut lexer, &mut syntax);
         let errors = vec![];
         Parser {
             lexer,
             curr_token,
             peek_token,
             errors,
+            syntax,
+            nesting: 0,
+            loop_depth: 0,
+        }
+    }
+
+    /// Concrete syntax tree of everything parsed so far.
+    pub fn into_syntax_tree(self) -> SyntaxNode<'a> {
+        self.syntax.finish()
+    }
+
+    // Returns the next significant token, handing the whitespace and comments
+    // before it to the syntax tree.
+    fn fetch_token(lexer: &mut Lexer<'a>, syntax: &mut SyntaxTreeBuilder<'a>) -> SpannedToken<'a> {
+        loop {
+            let token = lexer.next_token();
+            match token.token {
+                Token::WHITESPACE | Token::COMMENT => syntax.token(&token),
+                _ => return token,
+            }
         }
     }
 
     fn next_token(&mut self) {
-        self.curr_token = self.peek_token.clone();
-        self.peek_token = self.lexer.next_token();
+        self.nesting = self.nesting_after_curr();
+        if !self.peek_token_is(&Token::EOF) {
+            self.syntax.token(&self.peek_token);
+        }
+        let next = Self::fetch_token(&mut self.lexer, &mut self.syntax);
+        self.curr_token = std::mem::replace(&mut self.peek_token, next);
     }
 
-    pub fn parse_program(&mut self) -> Result<Vec<Statement>, Vec<ParseError>> {
-        let mut program = vec![];
-        while !self.curr_token_is(&Token::EOF) {
+    /// Parses the whole input into the syntax tree, returning every error
+    /// found on the way.
+    pub fn parse_program(&mut self) -> Result<(), Vec<ParseError>> {
+        self.parse_statements(&Token::EOF);
+
+        if !self.errors.is_empty() {
+            return Err(self.errors.clone());
+        }
+        Ok(())
+    }
+
+    // Parses statements up to `end`. A statement that fails to parse has its
+    // error recorded and is skipped, so that parsing carries on with the next
+    // one and every independent error gets reported.
+    fn parse_statements(&mut self, end: &Token) {
+        while !self.curr_token_is(end) && !self.curr_token_is(&Token::EOF) {
+            let checkpoint = self.syntax.checkpoint();
+            let nesting = self.nesting;
             match self.parse_statement() {
-                Ok(stat) => program.push(stat),
-                Err(err) => self.errors.push(err),
+                Ok(kind) => self.syntax.wrap(checkpoint, kind),
+                Err(err) => {
+                    self.errors.push(err);
+                    if self.synchronize(nesting) {
+                        // the `}` the statement ran into closes this block
+                        let closing = self.syntax.checkpoint();
+                        self.syntax.wrap_until(checkpoint, closing, SyntaxKind::Error);
+                        continue;
+                    }
+                    self.syntax.wrap(checkpoint, SyntaxKind::Error);
+                }
             }
 
             self.next_token();
         }
+    }
 
-        if self.errors.len() != 0 {
-            return Err(self.errors.clone());
+    // Skips the rest of a statement that failed to parse, which started at
+    // the given `nesting`. The current token is left on its last token: a
+    // `;`, or the token before the next `let`, `return`, EOF or a `}` closing
+    // the enclosing block. Returns true when the statement was instead cut
+    // short by a bracket closing an enclosing construct, which is then the
+    // current token and does not belong to the statement.
+    fn synchronize(&mut self, nesting: usize) -> bool {
+        loop {
+            match self.curr_token.token {
+                Token::EOF => return false,
+                Token::SEMICOLON if self.nesting == nesting => return false,
+                Token::RPAREN | Token::RBRACE | Token::RBRACKET
+                    if 0 < self.nesting && self.nesting <= nesting =>
+                {
+                    return true
+                }
+                _ => {}
+            }
+            if self.nesting_after_curr() == nesting {
+                match self.peek_token.token {
+                    Token::LET | Token::RETURN | Token::BREAK | Token::CONTINUE | Token::EOF => {
+                        return false
+                    }
+                    Token::RBRACE if nesting > 0 => return false,
+                    _ => {}
+                }
+            }
+            self.next_token();
         }
-        Ok(program)
     }
 
-    fn parse_statement(&mut self) -> Result<Statement, ParseError> {
-        match self.curr_token {
+    // Returns the kind of node the statement is wrapped in.
+    fn parse_statement(&mut self) -> Result<SyntaxKind, ParseError> {
+        match self.curr_token.token {
             Token::LET => self.parse_let_statements(),
             Token::RETURN => self.parse_return_statements(),
-            Token::ILLEGAL => Err(ParseError::illegal_token_error()),
+            Token::BREAK | Token::CONTINUE => self.parse_loop_control_statement(),
+            Token::ILLEGAL => Err(ParseError::illegal_token_error(self.curr_token.span)),
+            Token::ERROR(ref message) => Err(ParseError::lexer_error(message, self.curr_token.span)),
             _ => self.parse_expression_statements(),
         }
     }
 
-    fn parse_let_statements(&mut self) -> Result<Statement, ParseError> {
-        if let Token::IDENT(identifier) = &self.peek_token {
-            let identifier = identifier.clone();
+    // The bound pattern is a name, `_`, or an array or hash pattern to
+    // destructure the value with.
+    fn parse_let_statements(&mut self) -> Result<SyntaxKind, ParseError> {
+        if !matches!(
+            self.peek_token.token,
+            Token::IDENT(_) | Token::LBRACKET | Token::LBRACE
+        ) {
+            return Err(ParseError::parse_identifier_error(&self.peek_token));
+        }
+        self.next_token();
+        self.parse_pattern(&mut vec![])?;
+
+        self.expect_peek_token(&Token::ASSIGN)?;
+        self.next_token();
 
-            self.next_token();
+        self.parse_expression(Precedence::LOWEST)?;
 
-            self.expect_peek_token(&Token::ASSIGN)?;
+        if self.peek_token_is(&Token::SEMICOLON) {
             self.next_token();
+        }
 
-            let expression = self.parse_expression(Precedence::LOWEST)?;
+        Ok(SyntaxKind::LetStatement)
+    }
 
-            if !self.curr_token_is(&Token::SEMICOLON) {
-                self.next_token();
-            }
+    fn parse_return_statements(&mut self) -> Result<SyntaxKind, ParseError> {
+        self.next_token();
+        self.parse_expression(Precedence::LOWEST)?;
 
-            Ok(Statement::Let(identifier.clone(), expression))
-        } else {
-            Err(ParseError::parse_identifier_error(&self.peek_token))
+        if self.peek_token_is(&Token::SEMICOLON) {
+            self.next_token();
         }
-    }
 
-    fn parse_return_statements(&mut self) -> Result<Statement, ParseError> {
-        self.next_token();
-        let expression = self.parse_expression(Precedence::LOWEST)?;
+        Ok(SyntaxKind::ReturnStatement)
+    }
 
-        if !self.curr_token_is(&Token::SEMICOLON) {
+    fn parse_loop_control_statement(&mut self) -> Result<SyntaxKind, ParseError> {
+        if self.loop_depth == 0 {
+            return Err(ParseError::new(
+                format!("{} outside of a loop", self.curr_token.token),
+                self.curr_token.span,
+            ));
+        }
+        let kind = match self.curr_token.token {
+            Token::BREAK => SyntaxKind::BreakStatement,
+            _ => SyntaxKind::ContinueStatement,
+        };
+        if self.peek_token_is(&Token::SEMICOLON) {
             self.next_token();
         }
 
-        Ok(Statement::Return(expression))
+        Ok(kind)
     }
 
-    fn parse_expression_statements(&mut self) -> Result<Statement, ParseError> {
-        let expression = self.parse_expression(Precedence::LOWEST)?;
+    fn parse_expression_statements(&mut self) -> Result<SyntaxKind, ParseError> {
+        self.parse_expression(Precedence::LOWEST)?;
         if self.peek_token_is(&Token::SEMICOLON) {
             self.next_token();
         }
 
-        Ok(Statement::Expression(expression))
-    }
-
-    fn parse_expression(&mut self, precedence: Precedence) -> Result<Expression, ParseError> {
-        let mut left_expr = match &self.curr_token {
-            Token::IDENT(x) => Ok(Expression::Identifier(x.clone())),
-            Token::INT(x) => match x.parse::<i64>() {
-                Ok(num) => Ok(Expression::Literal(Literal::Integer(num))),
-                Err(_) => Err(ParseError::parse_integer_error(x)),
-            },
-            Token::BOOLEAN(b) => Ok(Expression::Literal(Literal::Bool(*b))),
-            Token::STRING(s)=>Ok(Expression::Literal(Literal::String(s.to_string()))),
-            Token::BANG | Token::MINUS => self.parse_prefix_expression(),
-            Token::LPAREN => self.parse_group_expression(),
-            Token::LBRACKET=>self.parse_array_literal(),
-            Token::LBRACE=>self.parse_hash_literal(),
-            Token::IF => self.parse_if_expression(),
-            Token::FUNCTION => self.parse_function_expression(),
-            _ => Err(ParseError::unrecognizable_token_error()),
-        };
+        Ok(SyntaxKind::ExpressionStatement)
+    }
+
+    fn parse_expression(&mut self, precedence: Precedence) -> Result<(), ParseError> {
+        let checkpoint = self.syntax.checkpoint();
+        let syntax_kind = prefix_syntax_kind(&self.curr_token.token);
+        match &self.curr_token.token {
+            Token::IDENT(_) | Token::INT(_) | Token::FLOAT(_) | Token::BOOLEAN(_) | Token::STRING(_) => {}
+            Token::TEMPLATE(parts) => {
+                let parts = parts.clone();
+                self.parse_template_string(&parts)?
+            }
+            Token::BANG | Token::MINUS | Token::TILDE => self.parse_prefix_expression()?,
+            Token::LPAREN => self.parse_group_expression()?,
+            Token::LBRACKET => self.parse_expression_list(&Token::RBRACKET)?,
+            Token::LBRACE => self.parse_hash_literal()?,
+            Token::IF => self.parse_if_expression()?,
+            Token::WHILE => self.parse_while_expression()?,
+            Token::FOR => self.parse_for_expression()?,
+            Token::MATCH => self.parse_match_expression()?,
+            Token::FUNCTION => self.parse_function_expression()?,
+            Token::PIPE | Token::OR => self.parse_lambda_expression()?,
+            Token::ERROR(message) => return Err(ParseError::lexer_error(message, self.curr_token.span)),
+            _ => return Err(ParseError::unrecognizable_token_error(&self.curr_token)),
+        }
+        self.syntax.wrap(checkpoint, syntax_kind);
 
         while !self.peek_token_is(&Token::SEMICOLON) && precedence < self.peek_precedence() {
-            match self.peek_token {
-                Token::PLUS
-                | Token::MINUS
-                | Token::ASTERISK
-                | Token::SLASH
-                | Token::EQ
-                | Token::NOTEQ
-                | Token::LT
-                | Token::GT => {
+            let syntax_kind = match self.peek_token.token {
+                Token::LPAREN => {
                     self.next_token();
-                    left_expr = self.parse_infix_expression(left_expr.unwrap());
+                    self.parse_func_call_expression()?;
+                    SyntaxKind::CallExpression
                 }
-                Token::LPAREN => {
+                Token::LBRACKET => {
+                    self.next_token();
+                    self.parse_index_expression()?;
+                    SyntaxKind::IndexExpression
+                }
+                Token::DOT => {
                     self.next_token();
-                    left_expr = self.parse_func_call_expression(left_expr.unwrap())
+                    self.parse_member_expression()?
                 }
-                Token::LBRACKET=>{
+                Token::AND | Token::OR => {
                     self.next_token();
-                    left_expr = self.parse_index_expression(left_expr.unwrap())
+                    self.parse_logical_expression()?;
+                    SyntaxKind::InfixExpression
+                }
+                Token::PIPELINE => {
+                    self.next_token();
+                    self.parse_pipeline_expression()?;
+                    SyntaxKind::PipelineExpression
+                }
+                Token::QUESTION => {
+                    self.next_token();
+                    self.parse_conditional_expression()?;
+                    SyntaxKind::ConditionalExpression
+                }
+                Token::ASSIGN
+                | Token::PLUSASSIGN
+                | Token::MINUSASSIGN
+                | Token::ASTERISKASSIGN
+                | Token::SLASHASSIGN => {
+                    self.next_token();
+                    self.parse_assign_expression(checkpoint)?;
+                    SyntaxKind::AssignExpression
+                }
+                _ => {
+                    self.next_token();
+                    self.parse_infix_expression()?;
+                    SyntaxKind::InfixExpression
+                }
+            };
+            self.syntax.wrap(checkpoint, syntax_kind);
+        }
 
-                },
-                _ => unreachable!(),
+        Ok(())
+    }
+
+    fn parse_prefix_expression(&mut self) -> Result<(), ParseError> {
+        self.next_token();
+        self.parse_expression(Precedence::PREFIX)
+    }
+
+    fn parse_infix_expression(&mut self) -> Result<(), ParseError> {
+        let precedence = match InfixOperator::from_token(&self.curr_token.token) {
+            // right-associative, so `2 ** 3 ** 2` is `2 ** (3 ** 2)`
+            Some(InfixOperator::Power) => Precedence::PREFIX,
+            Some(_) => token_to_precedence(&self.curr_token.token),
+            None => return Err(ParseError::unrecognizable_token_error(&self.curr_token)),
+        };
+        self.next_token();
+        self.parse_expression(precedence)
+    }
+
+    // Only checks the interpolated expressions, which lowering parses again
+    // from the string token.
+    fn parse_template_string(&mut self, parts: &[StringPart]) -> Result<(), ParseError> {
+        for part in parts {
+            if let StringPart::Expression(span) = part {
+                let lexer = Lexer::embedded(self.lexer.source(), *span);
+                let mut parser = Parser::new(lexer);
+                // an interpolation inside a loop body is still inside the loop
+                parser.loop_depth = self.loop_depth;
+                let expression = parser.parse_embedded_expression(*span);
+                self.errors.append(&mut parser.errors);
+                expression?;
             }
         }
 
-        left_expr
+        Ok(())
+    }
+
+    // Parses the single expression making up a `${...}` interpolation.
+    fn parse_embedded_expression(&mut self, span: Span) -> Result<(), ParseError> {
+        if self.curr_token_is(&Token::EOF) {
+            return Err(ParseError::new(
+                "Expected an expression in string interpolation".to_string(),
+                span,
+            ));
+        }
+        self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek_token(&Token::EOF)
     }
 
-    fn parse_prefix_expression(&mut self) -> Result<Expression, ParseError> {
-        let curr_tok = self.curr_token.clone();
+    fn parse_logical_expression(&mut self) -> Result<(), ParseError> {
+        let precedence = token_to_precedence(&self.curr_token.token);
         self.next_token();
-        let expr = self.parse_expression(Precedence::PREFIX)?;
+        self.parse_expression(precedence)
+    }
 
-        Ok(Expression::Prefix(curr_tok, Box::new(expr)))
+    // `x |> f(a)` is the call `f(x, a)`, and `x |> f` without an argument
+    // list is `f(x)`. Only the call target binds to the right of `|>`, so
+    // `x |> f() + 1` adds one to `f(x)`, while `a + b |> f()` passes `a + b`.
+    fn parse_pipeline_expression(&mut self) -> Result<(), ParseError> {
+        self.next_token();
+        self.parse_expression(Precedence::PREFIX)
     }
 
-    fn parse_infix_expression(&mut self, left_expr: Expression) -> Result<Expression, ParseError> {
-        let operand = self.curr_token.clone();
-        let precedence = token_to_precedence(&operand);
+    // `cond ? a : b` is an if expression whose branches hold one expression
+    // each. It is right-associative, so `a ? b : c ? d : e` nests in the
+    // alternative.
+    fn parse_conditional_expression(&mut self) -> Result<(), ParseError> {
         self.next_token();
-        let right_expr = self.parse_expression(precedence)?;
+        self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek_token(&Token::COLON)?;
+        self.next_token();
+        self.parse_expression(Precedence::ASSIGN)
+    }
 
-        Ok(Expression::Infix(
-            Box::new(left_expr),
-            operand,
-            Box::new(right_expr),
-        ))
+    // Assignments are right-associative, so `a = b = 1` assigns 1 to both.
+    // `target` is the checkpoint of the already parsed assignment target.
+    fn parse_assign_expression(&mut self, target: usize) -> Result<(), ParseError> {
+        let invalid = self
+            .syntax
+            .node_at(target)
+            .filter(|target| assigned_name(target).is_none());
+        if let Some(target) = invalid {
+            let target = lower_expression(self.lexer.source(), target)?;
+            return Err(ParseError::new(
+                format!("Invalid assignment target {}", target),
+                target.span,
+            ));
+        }
+        self.next_token();
+        self.parse_expression(Precedence::LOWEST)
     }
 
-    fn parse_group_expression(&mut self) -> Result<Expression, ParseError> {
+    fn parse_group_expression(&mut self) -> Result<(), ParseError> {
         self.next_token();
-        let expression = self.parse_expression(Precedence::LOWEST)?;
-        self.expect_peek_token(&Token::RPAREN)?;
-        Ok(expression)
+        self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek_token(&Token::RPAREN)
     }
 
-    fn parse_if_expression(&mut self) -> Result<Expression, ParseError> {
+    fn parse_if_expression(&mut self) -> Result<(), ParseError> {
         self.expect_peek_token(&Token::LPAREN)?;
         self.next_token();
-        let condition = self.parse_expression(Precedence::LOWEST)?;
+        self.parse_expression(Precedence::LOWEST)?;
         self.expect_peek_token(&Token::RPAREN)?;
         self.expect_peek_token(&Token::LBRACE)?;
-        let consequence = self.parse_block_statements()?;
-        let mut alternative = Option::None;
+        self.parse_block_statements()?;
         if self.peek_token_is(&Token::ELSE) {
             self.next_token();
-            self.expect_peek_token(&Token::LBRACE)?;
-            alternative = Some(self.parse_block_statements()?);
+            if self.peek_token_is(&Token::IF) {
+                // `else if` is lowered to an `else` block holding just the nested if
+                self.next_token();
+                let checkpoint = self.syntax.checkpoint();
+                self.parse_if_expression()?;
+                self.syntax.wrap(checkpoint, SyntaxKind::IfExpression);
+            } else {
+                self.expect_peek_token(&Token::LBRACE)?;
+                self.parse_block_statements()?;
+            }
         }
 
-        Ok(Expression::IfExpr(
-            Box::new(condition),
-            consequence,
-            alternative,
-        ))
+        Ok(())
     }
 
-    fn parse_block_statements(&mut self) -> Result<BlockStatement, ParseError> {
-        let mut statements = vec![];
+    fn parse_while_expression(&mut self) -> Result<(), ParseError> {
+        self.expect_peek_token(&Token::LPAREN)?;
         self.next_token();
-        while !self.curr_token_is(&Token::RBRACE) && !self.curr_token_is(&Token::EOF) {
-            let stat = self.parse_statement()?;
-            statements.push(stat);
+        self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek_token(&Token::RPAREN)?;
+        self.expect_peek_token(&Token::LBRACE)?;
+        self.parse_loop_body()
+    }
+
+    fn parse_for_expression(&mut self) -> Result<(), ParseError> {
+        self.expect_peek_token(&Token::LPAREN)?;
+        self.expect_peek_identifier()?;
+        if self.peek_token_is(&Token::COMMA) {
             self.next_token();
+            self.expect_peek_identifier()?;
         }
-        Ok(BlockStatement(statements))
+        self.expect_peek_token(&Token::IN)?;
+        self.next_token();
+        self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek_token(&Token::RPAREN)?;
+        self.expect_peek_token(&Token::LBRACE)?;
+        self.parse_loop_body()
     }
 
-    fn parse_function_expression(&mut self) -> Result<Expression, ParseError> {
+    fn parse_match_expression(&mut self) -> Result<(), ParseError> {
         self.expect_peek_token(&Token::LPAREN)?;
-
-        let params = self.parse_function_parameter()?;
+        self.next_token();
+        self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek_token(&Token::RPAREN)?;
         self.expect_peek_token(&Token::LBRACE)?;
 
-        let body = self.parse_block_statements()?;
+        while !self.peek_token_is(&Token::RBRACE) {
+            self.next_token();
+            self.parse_match_arm()?;
+            if !self.peek_token_is(&Token::RBRACE) {
+                self.expect_peek_token(&Token::COMMA)?;
+            }
+        }
+
+        self.expect_peek_token(&Token::RBRACE)
+    }
+
+    fn parse_match_arm(&mut self) -> Result<(), ParseError> {
+        let checkpoint = self.syntax.checkpoint();
+        self.parse_pattern(&mut vec![])?;
+        if self.peek_token_is(&Token::IF) {
+            self.next_token();
+            self.next_token();
+            self.parse_expression(Precedence::LOWEST)?;
+        }
+        self.expect_peek_token(&Token::FATARROW)?;
+        self.next_token();
+        self.parse_expression(Precedence::LOWEST)?;
+        self.syntax.wrap(checkpoint, SyntaxKind::MatchArm);
+
+        Ok(())
+    }
 
-        let expr = match params {
-            Some(params) => Expression::Func(Some(params), body),
-            None => Expression::Func(None, body),
+    // `names` collects the names bound so far by the whole pattern, so that
+    // binding one twice, as in `[x, ...x]`, is rejected.
+    fn parse_pattern(&mut self, names: &mut Vec<&'a str>) -> Result<(), ParseError> {
+        let checkpoint = self.syntax.checkpoint();
+        let syntax_kind = match self.curr_token.token {
+            Token::IDENT("_") => SyntaxKind::WildcardPattern,
+            Token::IDENT(name) => {
+                bind_name(names, name, self.curr_token.span)?;
+                SyntaxKind::BindingPattern
+            }
+            Token::LBRACKET => {
+                self.parse_array_pattern(names)?;
+                SyntaxKind::ArrayPattern
+            }
+            Token::LBRACE => {
+                self.parse_hash_pattern(names)?;
+                SyntaxKind::HashPattern
+            }
+            _ => {
+                self.parse_literal_pattern()?;
+                SyntaxKind::LiteralPattern
+            }
         };
+        self.syntax.wrap(checkpoint, syntax_kind);
 
-        Ok(expr)
+        Ok(())
     }
 
-    fn parse_function_parameter(&mut self) -> Result<Option<Vec<String>>, ParseError> {
-        if self.peek_token_is(&Token::RPAREN) {
+    // A number may be negated, as in `-1`.
+    fn parse_literal_pattern(&mut self) -> Result<(), ParseError> {
+        let negative = self.curr_token_is(&Token::MINUS);
+        if negative {
             self.next_token();
-            return Ok(None);
         }
-        let mut identifiers = vec![];
-        self.next_token();
+        match &self.curr_token.token {
+            Token::INT(_) | Token::FLOAT(_) => Ok(()),
+            Token::BOOLEAN(_) | Token::STRING(_) if !negative => Ok(()),
+            Token::ERROR(message) => Err(ParseError::lexer_error(message, self.curr_token.span)),
+            _ => Err(ParseError::parse_pattern_error(&self.curr_token)),
+        }
+    }
 
-        match &self.curr_token {
-            Token::IDENT(id) => identifiers.push(id.clone()),
-            other => return Err(ParseError::parse_identifier_error(other)),
+    fn parse_array_pattern(&mut self, names: &mut Vec<&'a str>) -> Result<(), ParseError> {
+        while !self.peek_token_is(&Token::RBRACKET) {
+            self.next_token();
+            if self.curr_token_is(&Token::ELLIPSIS) {
+                // the rest pattern has to be the last element
+                self.parse_rest_pattern(names)?;
+                break;
+            }
+            self.parse_pattern(names)?;
+            if !self.peek_token_is(&Token::RBRACKET) {
+                self.expect_peek_token(&Token::COMMA)?;
+            }
         }
 
-        while self.peek_token_is(&Token::COMMA) {
+        self.expect_peek_token(&Token::RBRACKET)
+    }
+
+    // `...name`, or a bare `...` that ignores the remaining elements.
+    fn parse_rest_pattern(&mut self, names: &mut Vec<&'a str>) -> Result<(), ParseError> {
+        let start = self.curr_token.span;
+        let checkpoint = self.syntax.checkpoint();
+        match self.peek_token.token {
+            Token::IDENT("_") => self.next_token(),
+            Token::IDENT(name) => {
+                self.next_token();
+                bind_name(names, name, start)?;
+            }
+            _ => {}
+        }
+        self.syntax.wrap(checkpoint, SyntaxKind::RestPattern);
+
+        Ok(())
+    }
+
+    // Each key is wrapped in a literal pattern node of its own.
+    fn parse_hash_pattern(&mut self, names: &mut Vec<&'a str>) -> Result<(), ParseError> {
+        while !self.peek_token_is(&Token::RBRACE) {
             self.next_token();
+            let checkpoint = self.syntax.checkpoint();
+            let negative = self.curr_token_is(&Token::MINUS);
+            self.parse_literal_pattern()?;
+            if let Token::FLOAT(x) = self.curr_token.token {
+                let key = if negative { -x } else { x };
+                return Err(ParseError::new(
+                    format!("{:?} cannot be used as a hashkey", key),
+                    self.curr_token.span,
+                ));
+            }
+            self.syntax.wrap(checkpoint, SyntaxKind::LiteralPattern);
+            self.expect_peek_token(&Token::COLON)?;
             self.next_token();
-
-            match &self.curr_token {
-                Token::IDENT(id) => identifiers.push(id.clone()),
-                other => return Err(ParseError::parse_identifier_error(other)),
+            self.parse_pattern(names)?;
+            if !self.peek_token_is(&Token::RBRACE) {
+                self.expect_peek_token(&Token::COMMA)?;
             }
         }
 
-        self.expect_peek_token(&Token::RPAREN)?;
+        self.expect_peek_token(&Token::RBRACE)
+    }
 
-        Ok(Some(identifiers))
+    fn parse_loop_body(&mut self) -> Result<(), ParseError> {
+        self.loop_depth += 1;
+        let body = self.parse_block_statements();
+        self.loop_depth -= 1;
+
+        body
     }
 
-    fn parse_func_call_expression(
-        &mut self,
-        expression: Expression,
-    ) -> Result<Expression, ParseError> {
-        let args = self.parse_expression_list(&Token::RPAREN)?;
-        Ok(Expression::FuncCall(Box::new(expression), args))
+    fn parse_block_statements(&mut self) -> Result<(), ParseError> {
+        let checkpoint = self.syntax.checkpoint();
+        self.next_token();
+        self.parse_statements(&Token::RBRACE);
+        if !self.curr_token_is(&Token::RBRACE) {
+            return Err(ParseError::parse_next_token_error(&Token::RBRACE, &self.curr_token));
+        }
+        self.syntax.wrap(checkpoint, SyntaxKind::BlockStatement);
+        Ok(())
     }
 
-    fn parse_expression_list(&mut self, end:&Token) -> Result<Vec<Expression>, ParseError> {
-        let mut args: Vec<Expression> = vec![];
-        if self.peek_token_is(end){
+    // `fn(x) { body }`, or `fn(x) => expr` with a single expression as body.
+    fn parse_function_expression(&mut self) -> Result<(), ParseError> {
+        self.expect_peek_token(&Token::LPAREN)?;
+
+        self.parse_function_parameter(&Token::RPAREN)?;
+        if self.peek_token_is(&Token::FATARROW) {
             self.next_token();
-            return Ok(args);
+            return self.parse_lambda_body();
         }
+        self.expect_peek_token(&Token::LBRACE)?;
+
+        // loops around a function literal do not extend into its body
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let body = self.parse_block_statements();
+        self.loop_depth = loop_depth;
+
+        body
+    }
 
+    // `|x, y| expr`, or `|| expr` without parameters, which is the same
+    // function as `fn(x, y) => expr`.
+    fn parse_lambda_expression(&mut self) -> Result<(), ParseError> {
+        if self.curr_token_is(&Token::OR) {
+            let checkpoint = self.syntax.checkpoint();
+            self.syntax.wrap(checkpoint, SyntaxKind::ParameterList);
+        } else {
+            self.parse_function_parameter(&Token::PIPE)?;
+        }
+        self.parse_lambda_body()
+    }
+
+    // The body extends as far to the right as possible, so `|x| x + 1` returns
+    // `x + 1`.
+    fn parse_lambda_body(&mut self) -> Result<(), ParseError> {
         self.next_token();
-        args.push(self.parse_expression(Precedence::LOWEST)?);
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let body = self.parse_expression(Precedence::LOWEST);
+        self.loop_depth = loop_depth;
 
-        while self.peek_token_is(&Token::COMMA) {
+        body
+    }
+
+    // `end` is the `)` or `|` closing the list.
+    fn parse_function_parameter(&mut self, end: &Token) -> Result<(), ParseError> {
+        // loops around a function literal do not extend into its defaults,
+        // which are evaluated by the call
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        let params = self.parse_parameter_list(end);
+        self.loop_depth = loop_depth;
+
+        params
+    }
+
+    fn parse_parameter_list(&mut self, end: &Token) -> Result<(), ParseError> {
+        let checkpoint = self.syntax.checkpoint();
+        if self.peek_token_is(end) {
             self.next_token();
+            self.syntax.wrap(checkpoint, SyntaxKind::ParameterList);
+            return Ok(());
+        }
+        let mut previous = PreviousParameters::default();
+        loop {
+            self.next_token();
+            self.parse_parameter(&mut previous, end)?;
+            if !self.peek_token_is(&Token::COMMA) {
+                break;
+            }
             self.next_token();
-            args.push(self.parse_expression(Precedence::LOWEST)?);
         }
 
         self.expect_peek_token(end)?;
-        Ok(args)
+        self.syntax.wrap(checkpoint, SyntaxKind::ParameterList);
+
+        Ok(())
     }
 
-    fn parse_array_literal(&mut self)->Result<Expression, ParseError>{
-        let array_elems = self.parse_expression_list(&Token::RBRACKET)?;
-        Ok(Expression::Literal(Literal::Array(array_elems)))
+    // Parameters with a default come after the ones without, and a rest
+    // parameter comes last.
+    fn parse_parameter(&mut self, previous: &mut PreviousParameters<'a>, end: &Token) -> Result<(), ParseError> {
+        let checkpoint = self.syntax.checkpoint();
+        if let (true, Some(name)) = (previous.has_rest, previous.names.last()) {
+            return Err(ParseError::new(
+                format!("Rest parameter {} must be the last parameter", name),
+                self.curr_token.span,
+            ));
+        }
+
+        if self.curr_token_is(&Token::ELLIPSIS) {
+            let name = self.expect_peek_identifier()?;
+            check_parameter_name(&previous.names, name, self.curr_token.span)?;
+            previous.names.push(name);
+            previous.has_rest = true;
+        } else {
+            let name = match self.curr_token.token {
+                Token::IDENT(name) => name,
+                _ => return Err(ParseError::parse_identifier_error(&self.curr_token)),
+            };
+            check_parameter_name(&previous.names, name, self.curr_token.span)?;
+            if self.peek_token_is(&Token::ASSIGN) {
+                self.next_token();
+                self.next_token();
+                // a default inside `|...|` stops before the closing `|`
+                let precedence = match end {
+                    Token::PIPE => Precedence::BITOR,
+                    _ => Precedence::LOWEST,
+                };
+                self.parse_expression(precedence)?;
+                previous.has_default = true;
+            } else if previous.has_default {
+                return Err(ParseError::new(
+                    format!("Parameter {} without a default follows a parameter with one", name),
+                    self.curr_token.span,
+                ));
+            }
+            previous.names.push(name);
+        }
+        self.syntax.wrap(checkpoint, SyntaxKind::Parameter);
+
+        Ok(())
+    }
+
+    fn parse_func_call_expression(&mut self) -> Result<(), ParseError> {
+        let checkpoint = self.syntax.checkpoint();
+        self.parse_arguments()?;
+        self.syntax.wrap(checkpoint, SyntaxKind::ArgumentList);
+        Ok(())
+    }
+
+    // Keyword arguments, `name: value`, follow the positional ones.
+    fn parse_arguments(&mut self) -> Result<(), ParseError> {
+        if self.peek_token_is(&Token::RPAREN) {
+            self.next_token();
+            return Ok(());
+        }
+
+        let mut has_keywords = false;
+        loop {
+            self.next_token();
+            match self.curr_token.token {
+                Token::IDENT(_) if self.peek_token_is(&Token::COLON) => {
+                    let checkpoint = self.syntax.checkpoint();
+                    self.next_token();
+                    self.next_token();
+                    self.parse_expression(Precedence::LOWEST)?;
+                    self.syntax.wrap(checkpoint, SyntaxKind::KeywordArgument);
+                    has_keywords = true;
+                }
+                _ if has_keywords => {
+                    return Err(ParseError::new(
+                        "Positional argument follows keyword argument".to_string(),
+                        self.curr_token.span,
+                    ));
+                }
+                _ => self.parse_expression(Precedence::LOWEST)?,
+            }
+            if !self.peek_token_is(&Token::COMMA) {
+                break;
+            }
+            self.next_token();
+        }
+
+        self.expect_peek_token(&Token::RPAREN)
     }
 
-    fn parse_hash_literal(&mut self)->Result<Expression, ParseError>{
-        let mut map = BTreeMap::new();
+    fn parse_expression_list(&mut self, end:&Token) -> Result<(), ParseError> {
+        if self.peek_token_is(end){
+            self.next_token();
+            return Ok(());
+        }
 
+        self.next_token();
+        self.parse_expression(Precedence::LOWEST)?;
+
+        while self.peek_token_is(&Token::COMMA) {
+            self.next_token();
+            self.next_token();
+            self.parse_expression(Precedence::LOWEST)?;
+        }
+
+        self.expect_peek_token(end)
+    }
+
+    fn parse_hash_literal(&mut self)->Result<(), ParseError>{
         while !self.peek_token_is(&Token::RBRACE) {
             self.next_token();
-            let key = self.parse_expression(Precedence::LOWEST)?;
+            self.parse_expression(Precedence::LOWEST)?;
 
             self.expect_peek_token(&Token::COLON)?;
             self.next_token();
 
-            let value = self.parse_expression(Precedence::LOWEST)?;
-
-            map.insert(key, value);
+            self.parse_expression(Precedence::LOWEST)?;
 
             if !self.peek_token_is(&Token::RBRACE){
                 self.expect_peek_token(&Token::COMMA)?;
             }
         }
 
-        self.expect_peek_token(&Token::RBRACE)?;
-        Ok(Expression::Literal(Literal::Hash(map)))
+        self.expect_peek_token(&Token::RBRACE)
     }
 
-    fn parse_index_expression(&mut self, left_expr: Expression)->Result<Expression, ParseError>{
+    fn parse_index_expression(&mut self)->Result<(), ParseError>{
         self.next_token();
-        let index = self.parse_expression(Precedence::LOWEST)?;
-        self.expect_peek_token(&Token::RBRACKET)?;
+        self.parse_expression(Precedence::LOWEST)?;
+        self.expect_peek_token(&Token::RBRACKET)
+    }
 
-        Ok(Expression::Index(Box::new(left_expr), Box::new(index)))
+    // `x.name`, or the method call `x.name(args)` when an argument list
+    // follows directly. Returns the kind of node the expression is wrapped in.
+    fn parse_member_expression(&mut self) -> Result<SyntaxKind, ParseError> {
+        self.expect_peek_identifier()?;
+        if !self.peek_token_is(&Token::LPAREN) {
+            return Ok(SyntaxKind::MemberExpression);
+        }
+
+        self.next_token();
+        let checkpoint = self.syntax.checkpoint();
+        self.parse_arguments()?;
+        self.syntax.wrap(checkpoint, SyntaxKind::ArgumentList);
+        Ok(SyntaxKind::MethodCallExpression)
     }
 
     fn curr_token_is(&self, token_type: &Token) -> bool {
-        self.curr_token == *token_type
+        self.curr_token.token == *token_type
     }
 
     fn peek_token_is(&self, token_type: &Token) -> bool {
-        self.peek_token == *token_type
+        self.peek_token.token == *token_type
+    }
+
+    fn expect_peek_identifier(&mut self) -> Result<&'a str, ParseError> {
+        match self.peek_token.token {
+            Token::IDENT(name) => {
+                self.next_token();
+                Ok(name)
+            }
+            _ => Err(ParseError::parse_identifier_error(&self.peek_token)),
+        }
     }
 
     fn expect_peek_token(&mut self, token_type: &Token) -> Result<(), ParseError> {
         if self.peek_token_is(token_type) {
             self.next_token();
             Ok(())
+        } else if let Token::ERROR(message) = &self.peek_token.token {
+            Err(ParseError::lexer_error(message, self.peek_token.span))
         } else {
             Err(ParseError::parse_next_token_error(
                 token_type,
@@ -341,8 +876,58 @@ impl Parser {
         }
     }
 
+    fn nesting_after_curr(&self) -> usize {
+        match self.curr_token.token {
+            Token::LPAREN | Token::LBRACE | Token::LBRACKET => self.nesting + 1,
+            Token::RPAREN | Token::RBRACE | Token::RBRACKET => self.nesting.saturating_sub(1),
+            _ => self.nesting,
+        }
+    }
+
     fn peek_precedence(&self) -> Precedence {
-        token_to_precedence(&self.peek_token)
+        token_to_precedence(&self.peek_token.token)
+    }
+}
+
+// Rejects a parameter named like an earlier one, which `fn(x, x)` would
+// otherwise silently shadow.
+fn check_parameter_name(previous: &[&str], name: &str, span: Span) -> Result<(), ParseError> {
+    if previous.contains(&name) {
+        return Err(ParseError::new(format!("Duplicate parameter {}", name), span));
+    }
+
+    Ok(())
+}
+
+// Records a name bound by a pattern, rejecting one the pattern bound before,
+// such as the second `x` of `[x, ...x]`.
+fn bind_name<'a>(names: &mut Vec<&'a str>, name: &'a str, span: Span) -> Result<(), ParseError> {
+    if names.contains(&name) {
+        return Err(ParseError::new(
+            format!("Duplicate binding {} in pattern", name),
+            span,
+        ));
+    }
+    names.push(name);
+
+    Ok(())
+}
+
+// Syntax tree node for the expression introduced by `token`.
+fn prefix_syntax_kind(token: &Token) -> SyntaxKind {
+    match token {
+        Token::IDENT(_) => SyntaxKind::Identifier,
+        Token::BANG | Token::MINUS | Token::TILDE => SyntaxKind::PrefixExpression,
+        Token::LPAREN => SyntaxKind::GroupExpression,
+        Token::LBRACKET => SyntaxKind::ArrayLiteral,
+        Token::LBRACE => SyntaxKind::HashLiteral,
+        Token::IF => SyntaxKind::IfExpression,
+        Token::WHILE => SyntaxKind::WhileExpression,
+        Token::FOR => SyntaxKind::ForExpression,
+        Token::MATCH => SyntaxKind::MatchExpression,
+        Token::FUNCTION | Token::PIPE | Token::OR => SyntaxKind::FunctionExpression,
+        Token::TEMPLATE(_) => SyntaxKind::TemplateString,
+        _ => SyntaxKind::Literal,
     }
 }
 
@@ -351,7 +936,7 @@ mod tests {
 
     use super::*;
 
-    fn test_helper(cases: &Vec<(&str, &str)>) {
+    fn test_helper(cases: &[(&str, &str)]) {
         for (input, expected) in cases {
             match start_parsing(input) {
                 Ok(program) => {
@@ -368,15 +953,42 @@ mod tests {
         }
     }
 
+    // Checks the first error reported for each input.
+    fn error_helper(cases: &[(&str, &str)]) {
+        for (input, expected) in cases {
+            match start_parsing(input) {
+                Ok(_) => panic!("expected an error for {}", input),
+                Err(errors) => assert_eq!(*expected, format!("{}", errors[0])),
+            }
+        }
+    }
+
     #[test]
     fn test_let_statement() {
         let test_cases = vec![
             ("let x = 5;", "let x = 5;"),
             ("let y = 10;", "let y = 10;"),
             ("let foobar = 16666;", "let foobar = 16666;"),
+            ("let [a, b, ...rest] = arr;", "let [a, b, ...rest] = arr;"),
+            ("let [first, [x, y]] = [1, [2, 3]]", "let [first, [x, y]] = 1, 2, 3;"),
+            (
+                "let {\"name\": name, \"age\": age} = person;",
+                "let {\"name\": name, \"age\": age} = person;",
+            ),
+            ("let _ = f();", "let _ = f();"),
         ];
 
         test_helper(&test_cases);
+
+        let test_cases = [
+            ("let 5 = x;", "line 1, column 5: Expected an Identifier but got 5"),
+            ("let [a b] = x;", "line 1, column 8: Expected token to be , but got b"),
+            ("let {name} = x;", "line 1, column 6: Expected a pattern but got name"),
+            ("let [a] == x;", "line 1, column 9: Expected token to be = but got =="),
+            ("let [a, a] = [1, 2];", "line 1, column 9: Duplicate binding a in pattern"),
+            ("let {\"k\": [b], \"j\": b} = h;", "line 1, column 21: Duplicate binding b in pattern"),
+        ];
+        error_helper(&test_cases);
     }
 
     #[test]
@@ -408,6 +1020,30 @@ mod tests {
         test_helper(&test_cases);
     }
 
+    #[test]
+    fn test_radix_integer_literal_expression() {
+        let test_cases = vec![
+            ("0xFF;", "255"),
+            ("0o17 + 0b1010", "(15+10)"),
+            ("1_000_000", "1000000"),
+        ];
+
+        test_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_float_literal_expression() {
+        let test_cases = vec![
+            ("3.14;", "3.14"),
+            ("-0.5;", "(-0.5)"),
+            ("2.0", "2.0"),
+            ("1e-9", "1e-9"),
+            ("1.5 * 2", "(1.5*2)"),
+        ];
+
+        test_helper(&test_cases);
+    }
+
     #[test]
     fn test_boolean_literal_expression() {
         let test_cases = vec![
@@ -468,14 +1104,227 @@ mod tests {
         test_helper(&test_cases);
     }
 
+    #[test]
+    fn test_assign_expression() {
+        let test_cases = vec![
+            ("x = 5;", "(x = 5)"),
+            ("x = y = 1 + 2", "(x = (y = (1+2)))"),
+            ("total += price * 2", "(total += (price*2))"),
+            ("a -= 1; b *= 2; c /= 3", "(a -= 1)(b *= 2)(c /= 3)"),
+            ("x = y == 1", "(x = (y==1))"),
+            ("f(n = 2)", "f((n = 2))"),
+        ];
+
+        test_helper(&test_cases);
+
+        let test_cases = [
+            ("1 + x = 3", "line 1, column 1: Invalid assignment target (1+x)"),
+            ("x == y = 1", "line 1, column 1: Invalid assignment target (x==y)"),
+            ("f() += 1; let y = ;", "line 1, column 1: Invalid assignment target f()"),
+        ];
+        error_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_while_expression() {
+        let test_cases = vec![
+            ("while (x < 10) { x += 1 }", "while (x<10) { (x += 1) }"),
+            (
+                "while (true) { if (x) { break; } continue }",
+                "while true { if x { break; }continue; }",
+            ),
+            ("while (a) { while (b) { break } }", "while a { while b { break; } }"),
+        ];
+
+        test_helper(&test_cases);
+
+        let test_cases = [
+            ("break;", "line 1, column 1: break outside of a loop"),
+            ("if (x) { continue }", "line 1, column 10: continue outside of a loop"),
+            (
+                "while (x) { let f = fn() { break }; }",
+                "line 1, column 28: break outside of a loop",
+            ),
+            ("while x { 1 }", "line 1, column 7: Expected token to be ( but got x"),
+        ];
+        error_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_for_expression() {
+        let test_cases = vec![
+            ("for (x in arr) { sum += x }", "for x in arr { (sum += x) }"),
+            ("for (k, v in {1: 2}) { k }", "for k, v in {1: 2} { k }"),
+            ("for (c in \"abc\") { break }", "for c in \"abc\" { break; }"),
+            ("for (i in range(1, 2 + 3)) { }", "for i in range(1, (2+3)) {  }"),
+        ];
+
+        test_helper(&test_cases);
+
+        let test_cases = [
+            ("for (1 in arr) {}", "line 1, column 6: Expected an Identifier but got 1"),
+            ("for (x, in arr) {}", "line 1, column 9: Expected an Identifier but got in"),
+            ("for (x of arr) {}", "line 1, column 8: Expected token to be in but got of"),
+            ("for (x in arr) { fn() { continue } }", "line 1, column 25: continue outside of a loop"),
+        ];
+        error_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_logical_expression() {
+        let test_cases = vec![
+            ("a && b", "(a&&b)"),
+            ("a || b && c", "(a||(b&&c))"),
+            ("a && b || c", "((a&&b)||c)"),
+            ("a || b || c", "((a||b)||c)"),
+            ("x < 1 || x > 5 && !done", "((x<1)||((x>5)&&(!done)))"),
+            ("a == b && c != d", "((a==b)&&(c!=d))"),
+            ("ok = a || b", "(ok = (a||b))"),
+        ];
+
+        test_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_comparison_and_arithmetic_operators() {
+        let test_cases = vec![
+            ("a <= b == c >= d", "((a<=b)==(c>=d))"),
+            ("a + b % c", "(a+(b%c))"),
+            ("a % b * c", "((a%b)*c)"),
+            ("2 ** 3 ** 2", "(2**(3**2))"),
+            ("-2 ** 2", "(-(2**2))"),
+            ("2 ** -1", "(2**(-1))"),
+            ("a * b ** c", "(a*(b**c))"),
+            ("a ** b * c", "((a**b)*c)"),
+            ("f(x) ** arr[0]", "(f(x)**(arr[0]))"),
+        ];
+
+        test_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_bitwise_operators() {
+        let test_cases = vec![
+            ("a | b ^ c & d", "(a|(b^(c&d)))"),
+            ("a & b | c", "((a&b)|c)"),
+            ("flags & mask == 0", "(flags&(mask==0))"),
+            ("a || b | c && d", "(a||((b|c)&&d))"),
+            ("1 << 2 + 3", "(1<<(2+3))"),
+            ("a << b < c >> d", "((a<<b)<(c>>d))"),
+            ("~a & ~b", "((~a)&(~b))"),
+        ];
+
+        test_helper(&test_cases);
+    }
+
     #[test]
     fn test_if_else_block() {
         let test_cases = vec![
             ("if(x<y){x};", "if (x<y) { x }"),
             ("if ( x < y ) { x } else { y }", "if (x<y) { x } else { y }"),
+            (
+                "if (x < y) { x } else if (x > y) { y } else { 0 }",
+                "if (x<y) { x } else { if (x>y) { y } else { 0 } }",
+            ),
+            (
+                "if (a) { 1 } else if (b) { 2 } else if (c) { 3 }",
+                "if a { 1 } else { if b { 2 } else { if c { 3 } } }",
+            ),
+        ];
+
+        test_helper(&test_cases);
+
+        let test_cases = [
+            ("if (a) { 1 } else if { 2 }", "line 1, column 22: Expected token to be ( but got {"),
+            ("if (a) { 1 } else if (b) 2", "line 1, column 26: Expected token to be { but got 2"),
+        ];
+        error_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_match_expression() {
+        let test_cases = vec![
+            ("match (x) { 1 => \"one\", _ => \"many\" }", "match x { 1 => \"one\", _ => \"many\" }"),
+            ("match (n) { -1 => a, 2.5 => b, true => c, n => n, }", "match n { -1 => a, 2.5 => b, true => c, n => n }"),
+            (
+                "match (xs) { [] => 0, [x] => x, [first, ...rest] => first + len(rest) }",
+                "match xs { [] => 0, [x] => x, [first, ...rest] => (first+len(rest)) }",
+            ),
+            ("match (xs) { [_, _, ...] => 2, [..._] => 0 }", "match xs { [_, _, ..._] => 2, [..._] => 0 }"),
+            (
+                "match (ev) { {\"type\": \"user\", \"id\": id} => id, {1: [a, b], true: _} => a }",
+                "match ev { {\"type\": \"user\", \"id\": id} => id, {1: [a, b], true: _} => a }",
+            ),
+            ("match (n) { x if x > 0 => 1, x if (x < 0) => -1, _ => 0 }", "match n { x if (x>0) => 1, x if (x<0) => (-1), _ => 0 }"),
+            ("match (x) {}", "match x {  }"),
+            ("let r = match (x) { _ => 1 } + 1;", "let r = (match x { _ => 1 }+1);"),
+        ];
+
+        test_helper(&test_cases);
+
+        let test_cases = [
+            ("match x { _ => 1 }", "line 1, column 7: Expected token to be ( but got x"),
+            ("match (x) { 1 -> 2 }", "line 1, column 15: Expected token to be => but got -"),
+            ("match (x) { 1 => 2 _ => 3 }", "line 1, column 20: Expected token to be , but got _"),
+            ("match (x) { a + 1 => 2 }", "line 1, column 15: Expected token to be => but got +"),
+            ("match (x) { f(y) => 2 }", "line 1, column 14: Expected token to be => but got ("),
+            ("match (x) { -true => 2 }", "line 1, column 14: Expected a pattern but got true"),
+            ("match (x) { [...rest, last] => 2 }", "line 1, column 21: Expected token to be ] but got ,"),
+            ("match (x) { {1.5: a} => 2 }", "line 1, column 14: 1.5 cannot be used as a hashkey"),
+            ("match (x) { {k: a} => 2 }", "line 1, column 14: Expected a pattern but got k"),
+            ("match ([1, 2]) { [x, ...x] => x }", "line 1, column 22: Duplicate binding x in pattern"),
+            ("match (p) { {\"a\": [y, z], \"b\": y} => y }", "line 1, column 32: Duplicate binding y in pattern"),
+        ];
+        error_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_pipeline_expression() {
+        let test_cases = vec![
+            ("x |> f(a)", "f(x, a)"),
+            ("x |> f()", "f(x)"),
+            ("x |> f", "f(x)"),
+            ("data |> filter(is_valid) |> map(normalize) |> len()", "len(map(filter(data, is_valid), normalize))"),
+            ("a + b |> f()", "f((a+b))"),
+            ("x |> f() + 1", "(f(x)+1)"),
+            ("a || b |> f()", "f((a||b))"),
+            ("n = xs |> len()", "(n = len(xs))"),
+            ("xs |> len() > 2 ? a : b", "if (len(xs)>2) { a } else { b }"),
+            ("x |> f(by: 2)", "f(x, by: 2)"),
+            ("x |> obj.method", "(obj.method)(x)"),
+            ("x |> make()(1)", "make()(x, 1)"),
         ];
 
         test_helper(&test_cases);
+
+        let test_cases = [
+            ("x |>", "line 1, column 5: Unrecognizable Token EOF"),
+            ("x |> |> f", "line 1, column 6: Unrecognizable Token |>"),
+        ];
+        error_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_conditional_expression() {
+        let test_cases = vec![
+            ("a ? b : c", "if a { b } else { c }"),
+            ("x < 0 ? -x : x", "if (x<0) { (-x) } else { x }"),
+            ("a ? b : c ? d : e", "if a { b } else { if c { d } else { e } }"),
+            ("a ? b ? c : d : e", "if a { if b { c } else { d } } else { e }"),
+            ("a || b ? 1 + 2 : 3", "if (a||b) { (1+2) } else { 3 }"),
+            ("m = a > b ? a : b", "(m = if (a>b) { a } else { b })"),
+            ("a ? x = 1 : 2", "if a { (x = 1) } else { 2 }"),
+            ("f(ok ? 1 : 2, 3)", "f(if ok { 1 } else { 2 }, 3)"),
+            ("{a ? 1 : 2: 3}", "{if a { 1 } else { 2 }: 3}"),
+        ];
+
+        test_helper(&test_cases);
+
+        let test_cases = [
+            ("a ? b", "line 1, column 6: Expected token to be : but got EOF"),
+            ("a ? b : x = 1", "line 1, column 1: Invalid assignment target if a { b } else { x }"),
+        ];
+        error_helper(&test_cases);
     }
 
     #[test]
@@ -484,9 +1333,102 @@ mod tests {
             ("fn() {};", "fn() {  }"),
             ("fn(x) {};", "fn(x) {  }"),
             ("fn(x,y,z) {};", "fn(x, y, z) {  }"),
+            ("fn(x, y = 10) {};", "fn(x, y = 10) {  }"),
+            ("fn(x = 1, y = x * 2) {};", "fn(x = 1, y = (x*2)) {  }"),
+            ("fn(first, ...others) {};", "fn(first, ...others) {  }"),
+            ("fn(a, b = [], ...c) {};", "fn(a, b = , ...c) {  }"),
+            ("fn(...all) {};", "fn(...all) {  }"),
+        ];
+
+        test_helper(&test_cases);
+
+        let test_cases = [
+            ("fn(x = 1, y) {}", "line 1, column 11: Parameter y without a default follows a parameter with one"),
+            ("fn(...rest, x) {}", "line 1, column 13: Rest parameter rest must be the last parameter"),
+            ("fn(...) {}", "line 1, column 7: Expected an Identifier but got )"),
+            ("fn(...rest = 1) {}", "line 1, column 12: Expected token to be ) but got ="),
+            ("fn(x, 1) {}", "line 1, column 7: Expected an Identifier but got 1"),
+            (
+                "while (true) { fn(a = if (true) { break } else { 1 }) { a } }",
+                "line 1, column 35: break outside of a loop",
+            ),
+            ("for (i in r) { |b = if (i) { continue } else { 1 }| b }", "line 1, column 30: continue outside of a loop"),
+            ("fn(x, x) { x }(1, 2)", "line 1, column 7: Duplicate parameter x"),
+            ("fn(a, b = 1, ...a) {}", "line 1, column 17: Duplicate parameter a"),
+            ("|y, y| y", "line 1, column 5: Duplicate parameter y"),
+        ];
+        error_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_member_expression() {
+        let test_cases = vec![
+            ("person.name", "(person.name)"),
+            ("a.b.c", "((a.b).c)"),
+            ("arr.rest().push(1).len()", "arr.rest().push(1).len()"),
+            ("xs.map(|x| x * 2, step: 1)", "xs.map(fn(x) { (x*2) }, step: 1)"),
+            ("-a.b", "(-(a.b))"),
+            ("a.b + c.d * 2", "((a.b)+((c.d)*2))"),
+            ("h.items[0].name", "(((h.items)[0]).name)"),
+            ("f(x).y", "(f(x).y)"),
+            ("1.abs()", "1.abs()"),
         ];
 
         test_helper(&test_cases);
+
+        let test_cases = [
+            ("a.", "line 1, column 3: Expected an Identifier but got EOF"),
+            ("a.1", "line 1, column 3: Expected an Identifier but got 1"),
+            ("a.f(x: 1, 2)", "line 1, column 11: Positional argument follows keyword argument"),
+            ("user.age = 3", "line 1, column 1: Invalid assignment target (user.age)"),
+        ];
+        error_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_lambda_expression() {
+        let test_cases = vec![
+            ("|x| x * 2", "fn(x) { (x*2) }"),
+            ("|a, b| a + b", "fn(a, b) { (a+b) }"),
+            ("|| 42", "fn() { 42 }"),
+            ("|x, y = 1| x | y", "fn(x, y = 1) { (x|y) }"),
+            ("|...xs| len(xs)", "fn(...xs) { len(xs) }"),
+            ("fn(x) => x * 2", "fn(x) { (x*2) }"),
+            ("fn() => 1 + 1", "fn() { (1+1) }"),
+            ("map(xs, |x| x + 1)", "map(xs, fn(x) { (x+1) })"),
+            ("|x| |y| x + y", "fn(x) { fn(y) { (x+y) } }"),
+            ("let twice = fn(f) => |x| f(f(x));", "let twice = fn(f) { fn(x) { f(f(x)) } };"),
+            ("a | b || c", "((a|b)||c)"),
+        ];
+
+        test_helper(&test_cases);
+
+        let test_cases = [
+            ("|x x", "line 1, column 4: Expected token to be | but got x"),
+            ("|x|", "line 1, column 4: Unrecognizable Token EOF"),
+            ("fn(x) =>", "line 1, column 9: Unrecognizable Token EOF"),
+            ("while (true) { |x| break }", "line 1, column 20: Unrecognizable Token break"),
+        ];
+        error_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_keyword_arguments() {
+        let test_cases = vec![
+            ("f(y: 2, x: 1)", "f(y: 2, x: 1)"),
+            ("f(1, scale: 2 * 3)", "f(1, scale: (2*3))"),
+            ("f(a ? b : c, d: {k: v})", "f(if a { b } else { c }, d: {k: v})"),
+            ("f(x)(y: 1)", "f(x)(y: 1)"),
+        ];
+
+        test_helper(&test_cases);
+
+        let test_cases = [
+            ("f(x: 1, 2)", "line 1, column 9: Positional argument follows keyword argument"),
+            ("f(x: )", "line 1, column 6: Unrecognizable Token )"),
+            ("f(1 + x: 2)", "line 1, column 8: Expected token to be ) but got :"),
+        ];
+        error_helper(&test_cases);
     }
 
     #[test]
@@ -500,4 +1442,136 @@ mod tests {
         ];
         test_helper(&test_cases);
     }
+
+    #[test]
+    fn test_string_literal_expression() {
+        let test_cases = vec![
+            (r#""hello world";"#, r#""hello world""#),
+            (r#""say \"hi\"\n";"#, r#""say \"hi\"\n""#),
+        ];
+
+        test_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_string_interpolation() {
+        let test_cases = vec![
+            (r#""hello ${name}!";"#, r#""hello ${name}!""#),
+            (r#""${a + b * 2}""#, r#""${(a+(b*2))}""#),
+            (r#""${ {"k": "}"}["k"] } \${x}""#, r#""${({"k": "}"}["k"])} \${x}""#),
+            (r#""outer ${"inner ${x}"}""#, r#""outer ${"inner ${x}"}""#),
+        ];
+
+        test_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_expression_spans() {
+        let program = match start_parsing("let x = 1;\nadd(x,  2 * y)") {
+            Ok(Node::Program(program)) => program,
+            Err(_) => panic!(),
+        };
+
+        let call = &program[1];
+        assert_eq!((2, 1, 11, 25), (call.span.line, call.span.column, call.span.start, call.span.end));
+
+        if let StatementKind::Expression(Expression {
+            kind: ExpressionKind::FuncCall(_, args, _),
+            ..
+        }) = &call.kind
+        {
+            let span = args[1].span;
+            assert_eq!((2, 9, 19, 24), (span.line, span.column, span.start, span.end));
+        } else {
+            panic!()
+        }
+    }
+
+    #[test]
+    fn test_error_recovery() {
+        let test_cases = [
+            (") + 1", vec!["line 1, column 1: Unrecognizable Token )"]),
+            (
+                "let = 5; let y = ; let z = 3;",
+                vec![
+                    "line 1, column 5: Expected an Identifier but got =",
+                    "line 1, column 18: Unrecognizable Token ;",
+                ],
+            ),
+            (
+                "let f = fn(x) {\n  x + ;\n  let y = ;\n  y\n};\nf(1 + );",
+                vec![
+                    "line 2, column 7: Unrecognizable Token ;",
+                    "line 3, column 11: Unrecognizable Token ;",
+                    "line 6, column 7: Unrecognizable Token )",
+                ],
+            ),
+            (
+                "let f = fn() { x + }; let g = (1;",
+                vec![
+                    "line 1, column 20: Unrecognizable Token }",
+                    "line 1, column 33: Expected token to be ) but got ;",
+                ],
+            ),
+            ("let h = {1: }; 2", vec!["line 1, column 13: Unrecognizable Token }"]),
+            ("if (x) { 1 ", vec!["line 1, column 12: Expected token to be } but got EOF"]),
+            (
+                "}}}; let a = ;",
+                vec![
+                    "line 1, column 1: Unrecognizable Token }",
+                    "line 1, column 14: Unrecognizable Token ;",
+                ],
+            ),
+            (
+                "\"${fn() { x + }}\"; \"${}\"",
+                vec![
+                    "line 1, column 15: Unrecognizable Token }",
+                    "line 1, column 23: Expected an expression in string interpolation",
+                ],
+            ),
+        ];
+
+        for (input, expected) in test_cases {
+            match start_parsing(input) {
+                Ok(_) => panic!("expected errors for {}", input),
+                Err(errors) => {
+                    let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                    assert_eq!(expected, errors, "{}", input);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_statements_without_semicolons() {
+        let test_cases = vec![
+            ("let x = 5 let y = x\nreturn y", "let x = 5;let y = x;return y;"),
+            ("let a = 1 a", "let a = 1;a"),
+        ];
+
+        test_helper(&test_cases);
+    }
+
+    #[test]
+    fn test_error_positions() {
+        let test_cases = [
+            ("let = 5;", "line 1, column 5: Expected an Identifier but got ="),
+            ("let x = 5;\nif (x { x }", "line 2, column 7: Expected token to be ) but got {"),
+            ("1 +\n  )", "line 2, column 3: Unrecognizable Token )"),
+            ("let x = 1; /* never closed", "line 1, column 12: unterminated block comment"),
+            ("add(1 /* never closed", "line 1, column 7: unterminated block comment"),
+            ("let s = \"oops;", "line 1, column 9: unterminated string literal"),
+            ("puts(\"a\\qb\")", "line 1, column 8: unknown escape sequence \\q"),
+            ("\"${}\"", "line 1, column 4: Expected an expression in string interpolation"),
+            ("\"a ${x y}\"", "line 1, column 8: Expected token to be EOF but got y"),
+            ("\"a\n ${1 +\n )}\"", "line 3, column 2: Unrecognizable Token )"),
+            ("\"${x\"", "line 1, column 2: unterminated string interpolation"),
+            (
+                "let big = 1 +\n    99999999999999999999;",
+                "line 2, column 5: integer literal 99999999999999999999 is out of range",
+            ),
+        ];
+
+        error_helper(&test_cases);
+    }
 }
@@ -1,25 +1,52 @@
 use crate::token::Token;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(PartialEq, PartialOrd)]
 pub enum Precedence {
     LOWEST,
+    ASSIGN,      // = or +=
+    CONDITIONAL, // ? :
+    PIPELINE,    // |>
+    OR,          // ||
+    AND,         // &&
+    BITOR,       // |
+    BITXOR,      // ^
+    BITAND,      // &
     EQUALS,      // ==
 	LESSGREATER, // > or <
+    SHIFT,       // << or >>
 	SUM,         // +
 	PRODUCT,     // *
 	PREFIX,      // -X or !X
+    POWER,       // **, binding tighter than a prefix operator on its left
 	CALL,
-    INDEX        // myFunction(X)
+    INDEX,       // myFunction(X)
+    MEMBER,      // x.key or x.method()
 }
 
 pub fn token_to_precedence(token: &Token)->Precedence{
     match  token {
+        Token::ASSIGN
+        | Token::PLUSASSIGN
+        | Token::MINUSASSIGN
+        | Token::ASTERISKASSIGN
+        | Token::SLASHASSIGN => Precedence::ASSIGN,
+        Token::QUESTION => Precedence::CONDITIONAL,
+        Token::PIPELINE => Precedence::PIPELINE,
+        Token::OR => Precedence::OR,
+        Token::AND => Precedence::AND,
+        Token::PIPE => Precedence::BITOR,
+        Token::CARET => Precedence::BITXOR,
+        Token::AMPERSAND => Precedence::BITAND,
         Token::EQ | Token::NOTEQ=>Precedence::EQUALS,
-        Token::GT | Token::LT=>Precedence::LESSGREATER,
+        Token::SHL | Token::SHR => Precedence::SHIFT,
+        Token::GT | Token::LT | Token::GTEQ | Token::LTEQ=>Precedence::LESSGREATER,
         Token::PLUS | Token::MINUS=>Precedence::SUM,
-        Token::SLASH | Token::ASTERISK=>Precedence::PRODUCT,
+        Token::SLASH | Token::ASTERISK | Token::PERCENT=>Precedence::PRODUCT,
+        Token::POWER => Precedence::POWER,
         Token::LPAREN=>Precedence::CALL,
         Token::LBRACKET=>Precedence::INDEX,
+        Token::DOT => Precedence::MEMBER,
         _=>Precedence::LOWEST
     }
 }
\ No newline at end of file
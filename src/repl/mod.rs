@@ -2,7 +2,7 @@ use std::{
     io::{stdout, BufRead, BufReader, Read, Write}, cell::RefCell, rc::Rc,
 };
 
-use crate::{lexer, parser, evaluator::eval, object::environment};
+use crate::{parser, evaluator::eval, object::environment};
 
 pub fn start(input: impl Read, _output: impl Write) {
     let mut reader = BufReader::new(input);
@@ -22,9 +22,7 @@ pub fn start(input: impl Read, _output: impl Write) {
             println!("exit");
             std::process::exit(0)
         } else {
-            let  l = lexer::Lexer::new(&input);
-            let mut p = parser::Parser::new(l);
-            let program = p.parse_program();
+            let program = parser::start_parsing(&input);
             input.clear();
             match program {
                 Err(errors) => {
@@ -33,7 +31,7 @@ pub fn start(input: impl Read, _output: impl Write) {
                     }
                 }
                 Ok(p) => {
-                    let evaluated = eval(crate::ast::Node::Program(p), env.clone());
+                    let evaluated = eval(p, env.clone());
                     match evaluated{
                         Ok(res)=>{
 
@@ -1,31 +1,93 @@
-use std::{fmt};
+use std::{borrow::Cow, fmt};
 
+/// Location of a token or syntax node in the source text.
+///
+/// `start` and `end` are byte offsets into the source, `line` and `column`
+/// are 1-based and point at the first character of the span.
+#[derive(Debug, PartialEq, Clone, Copy, Default, PartialOrd, Eq, Hash, Ord)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// Returns a span covering both `self` and `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct SpannedToken<'a> {
+    pub token: Token<'a>,
+    pub span: Span,
+}
 
-#[derive(Debug, PartialEq, Clone, PartialOrd, Eq, Hash, Ord)]
-pub enum Token  {
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Clone)]
+pub enum Token<'a>  {
     ILLEGAL,
+    // malformed input detected by the lexer, with a description of the problem
+    ERROR(String),
     EOF,
+    // trivia, only produced by a lexer built `with_trivia`
+    WHITESPACE,
+    COMMENT,
 
-    IDENT(String),
+    IDENT(&'a str),
 
-    INT(String),
+    INT(i64),
+    FLOAT(f64),
     BOOLEAN(bool),
     ASSIGN,
+    PLUSASSIGN,
+    MINUSASSIGN,
+    ASTERISKASSIGN,
+    SLASHASSIGN,
     PLUS,
     MINUS,
     BANG,
     ASTERISK,
     SLASH,
+    PERCENT,
+    POWER,
+    AMPERSAND,
+    PIPE,
+    CARET,
+    TILDE,
+    SHL,
+    SHR,
 
     LT,
     GT,
+    LTEQ,
+    GTEQ,
 
     EQ,
     NOTEQ,
 
+    AND,
+    OR,
+    PIPELINE,
+
     COMMA,
     SEMICOLON,
     COLON,
+    QUESTION,
+    FATARROW,
+    ELLIPSIS,
+    DOT,
 
     LPAREN,
     RPAREN,
@@ -39,13 +101,30 @@ pub enum Token  {
     IF,
     ELSE,
     RETURN,
+    WHILE,
+    FOR,
+    IN,
+    BREAK,
+    CONTINUE,
+    MATCH,
+
+    STRING(Cow<'a, str>),
+    // string containing `${...}` interpolations
+    TEMPLATE(Vec<StringPart<'a>>),
+}
 
-    STRING(String)
+/// Piece of an interpolated string literal.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StringPart<'a> {
+    // text with escapes decoded
+    Text(Cow<'a, str>),
+    // source of an embedded expression, without the surrounding `${` and `}`
+    Expression(Span),
 }
 
 
 
-pub fn lookup_ident(ident: &str) -> Token {
+pub fn lookup_ident(ident: &str) -> Token<'_> {
     match ident {
         "fn" => Token::FUNCTION,
         "let" => Token::LET,
@@ -54,28 +133,56 @@ pub fn lookup_ident(ident: &str) -> Token {
         "if" => Token::IF,
         "else" => Token::ELSE,
         "return" => Token::RETURN,
-        _ => Token::IDENT(ident.to_string()),
+        "while" => Token::WHILE,
+        "for" => Token::FOR,
+        "in" => Token::IN,
+        "break" => Token::BREAK,
+        "continue" => Token::CONTINUE,
+        "match" => Token::MATCH,
+        _ => Token::IDENT(ident),
     }
 }
 
-impl fmt::Display for Token {
+impl fmt::Display for Token<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Token::IDENT(id) => write!(f, "{}", id),
             Token::INT(i) => write!(f, "{}", i),
+            Token::FLOAT(x) => write!(f, "{:?}", x),
             Token::BOOLEAN(b) => write!(f, "{}", b),
             Token::ASSIGN => write!(f, "="),
+            Token::PLUSASSIGN => write!(f, "+="),
+            Token::MINUSASSIGN => write!(f, "-="),
+            Token::ASTERISKASSIGN => write!(f, "*="),
+            Token::SLASHASSIGN => write!(f, "/="),
             Token::PLUS => write!(f, "+"),
             Token::MINUS => write!(f, "-"),
             Token::BANG => write!(f, "!"),
             Token::ASTERISK => write!(f, "*"),
             Token::SLASH => write!(f, "/"),
+            Token::PERCENT => write!(f, "%"),
+            Token::POWER => write!(f, "**"),
+            Token::AMPERSAND => write!(f, "&"),
+            Token::PIPE => write!(f, "|"),
+            Token::CARET => write!(f, "^"),
+            Token::TILDE => write!(f, "~"),
+            Token::SHL => write!(f, "<<"),
+            Token::SHR => write!(f, ">>"),
             Token::LT => write!(f, "<"),
             Token::GT => write!(f, ">"),
+            Token::LTEQ => write!(f, "<="),
+            Token::GTEQ => write!(f, ">="),
             Token::EQ => write!(f, "=="),
             Token::NOTEQ => write!(f, "!="),
+            Token::AND => write!(f, "&&"),
+            Token::OR => write!(f, "||"),
+            Token::PIPELINE => write!(f, "|>"),
             Token::COMMA => write!(f, ","),
             Token::COLON => write!(f, ":"),
+            Token::QUESTION => write!(f, "?"),
+            Token::FATARROW => write!(f, "=>"),
+            Token::ELLIPSIS => write!(f, "..."),
+            Token::DOT => write!(f, "."),
             Token::SEMICOLON => write!(f, ";"),
             Token::LPAREN => write!(f, "("),
             Token::RPAREN => write!(f, ")"),
@@ -88,9 +195,19 @@ impl fmt::Display for Token {
             Token::RETURN => write!(f, "return"),
             Token::IF => write!(f, "if"),
             Token::ELSE => write!(f, "else"),
+            Token::WHILE => write!(f, "while"),
+            Token::FOR => write!(f, "for"),
+            Token::IN => write!(f, "in"),
+            Token::BREAK => write!(f, "break"),
+            Token::CONTINUE => write!(f, "continue"),
+            Token::MATCH => write!(f, "match"),
             Token::EOF=>write!(f, "EOF"),
+            Token::WHITESPACE => write!(f, "whitespace"),
+            Token::COMMENT => write!(f, "comment"),
             Token::ILLEGAL => write!(f, "invalid token"),
+            Token::ERROR(message) => write!(f, "{}", message),
             Token::STRING(s) => write!(f, "{}",s),
+            Token::TEMPLATE(_) => write!(f, "interpolated string"),
         }
     }
 }